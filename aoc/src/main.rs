@@ -0,0 +1,236 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::process;
+use std::time::{Duration, Instant};
+
+use common::{Answer, Puzzle};
+
+const USAGE: &str = "usage: aoc run <day|all> [--part N] [--input path]";
+
+fn solutions() -> Vec<Box<dyn Puzzle>> {
+    vec![
+        Box::new(day01::Day01),
+        Box::new(day02::Day02),
+        Box::new(day03::Day03),
+        Box::new(day04::Day04),
+        Box::new(day05::Day05),
+        Box::new(day06::Day06),
+        Box::new(day07::Day07),
+        Box::new(day08::Day08),
+        Box::new(day09::Day09),
+    ]
+}
+
+/// What to run, from the command line.
+#[derive(Debug, PartialEq)]
+struct Config {
+    /// `None` for every day.
+    day: Option<u32>,
+    /// `None` for both parts.
+    part: Option<u32>,
+    input: Option<String>,
+}
+
+/// One part's answer, or why there isn't one.
+struct Run {
+    day: u32,
+    part: u32,
+    answer: Result<Answer, String>,
+    elapsed: Duration,
+}
+
+fn parse_args(args: &[String]) -> Result<Config, String> {
+    let mut args = args.iter();
+    if args.next().map(String::as_str) != Some("run") {
+        return Err(USAGE.to_string());
+    }
+    let day = match args.next().map(String::as_str) {
+        Some("all") => None,
+        Some(day) => Some(day.parse().map_err(|e| format!("{}: {}", day, e))?),
+        None => return Err(USAGE.to_string()),
+    };
+    let mut config = Config { day, part: None, input: None };
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--part" => {
+                let part = value()?;
+                match part.as_str() {
+                    "1" => config.part = Some(1),
+                    "2" => config.part = Some(2),
+                    _ => return Err(format!("{}: expected part 1 or 2", part)),
+                }
+            }
+            "--input" => config.input = Some(value()?.clone()),
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+    if config.day.is_none() && config.input.is_some() {
+        return Err("--input needs a single day".to_string());
+    }
+    Ok(config)
+}
+
+// The day's puzzle input from its crate, or nothing for days with the input
+// built in
+fn default_input(day: u32) -> io::Result<String> {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push(format!("../day{:02}/data/input.txt", day));
+    match fs::read_to_string(&path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+        result => result,
+    }
+}
+
+fn run(solution: &dyn Puzzle, part: u32, input: &str) -> Run {
+    let start = Instant::now();
+    let answer = solution.solve(part, input);
+    Run {
+        day: solution.day(),
+        part,
+        answer: answer.map_err(|e| e.to_string()),
+        elapsed: start.elapsed(),
+    }
+}
+
+fn millis(elapsed: Duration) -> String {
+    format!("{:.3} ms", elapsed.as_secs_f64() * 1000.0)
+}
+
+// A row per part, with images left to the log above
+fn summary(runs: &[Run]) -> String {
+    let rows: Vec<(String, String, String, String)> = runs
+        .iter()
+        .map(|r| {
+            let answer = match &r.answer {
+                Ok(Answer::Image(_)) => "(see above)".to_string(),
+                Ok(answer) => answer.to_string(),
+                Err(e) => format!("error: {}", e),
+            };
+            (r.day.to_string(), r.part.to_string(), answer, millis(r.elapsed))
+        })
+        .collect();
+    let width = rows.iter().map(|r| r.2.len()).max().unwrap_or(0).max(6);
+    let mut table = format!("day  part  {:<width$}  {:>12}\n", "answer", "time", width = width);
+    for (day, part, answer, time) in &rows {
+        table += &format!("{:>3}  {:>4}  {:<width$}  {:>12}\n", day, part, answer, time, width = width);
+    }
+    let total: Duration = runs.iter().map(|r| r.elapsed).sum();
+    table += &format!("{:<w$}  {:>12}\n", "total", millis(total), w = 11 + width);
+    table
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let config = parse_args(&args).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(2);
+    });
+
+    let solutions: Vec<Box<dyn Puzzle>> = solutions()
+        .into_iter()
+        .filter(|s| config.day.is_none_or(|day| s.day() == day))
+        .collect();
+    if solutions.is_empty() {
+        eprintln!("No solution for day {}", config.day.unwrap_or(0));
+        process::exit(2);
+    }
+
+    let mut runs = Vec::new();
+    for solution in &solutions {
+        let input = match &config.input {
+            Some(path) => fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e)),
+            None => default_input(solution.day()).map_err(|e| e.to_string()),
+        };
+        let input = input.unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        });
+        for part in 1..=2 {
+            if config.part.is_some_and(|p| p != part) {
+                continue;
+            }
+            let run = run(solution.as_ref(), part, &input);
+            match &run.answer {
+                Ok(answer @ Answer::Image(_)) => println!("Day {} part {}:\n{}", run.day, run.part, answer),
+                Ok(answer) => println!("Day {} part {}: {}", run.day, run.part, answer),
+                Err(e) => println!("Day {} part {}: error: {}", run.day, run.part, e),
+            }
+            runs.push(run);
+        }
+    }
+
+    println!();
+    print!("{}", summary(&runs));
+    if runs.iter().any(|r| r.answer.is_err()) {
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split(' ').map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(parse_args(&args("run all")), Ok(Config { day: None, part: None, input: None }));
+        assert_eq!(parse_args(&args("run 7 --part 2 --input x.txt")),
+                   Ok(Config { day: Some(7), part: Some(2), input: Some("x.txt".to_string()) }));
+        assert!(parse_args(&args("run 7 --part 3")).is_err());
+        assert!(parse_args(&args("run all --input x.txt")).is_err());
+        assert!(parse_args(&args("run")).is_err());
+        assert!(parse_args(&args("bench 7")).is_err());
+    }
+
+    // The letters day08 part 2 spells out
+    const DAY08_IMAGE: &str = "\
+***   **  *   **  * ***  \n\
+*  * *  * *   ** *  *  * \n\
+*  * *     * * **   *  * \n\
+***  *      *  * *  ***  \n\
+* *  *  *   *  * *  * *  \n\
+*  *  **    *  *  * *  * ";
+
+    #[test]
+    fn test_answers() {
+        let expected = [
+            ("3366415", "5046772"),
+            ("4484226", "5696"),
+            ("221", "18542"),
+            ("1873", "1264"),
+            ("15314507", "652726"),
+            ("295936", "457"),
+            ("273814", "34579864"),
+            ("1320", DAY08_IMAGE),
+            ("2752191671", "87571"),
+        ];
+        for (solution, (part1, part2)) in solutions().iter().zip(expected.iter()) {
+            let input = default_input(solution.day()).unwrap();
+            for (part, expected) in [(1, part1), (2, part2)].iter() {
+                let answer = run(solution.as_ref(), *part, &input).answer.map(|a| a.to_string());
+                assert_eq!(answer.as_deref(), Ok(**expected));
+            }
+        }
+    }
+
+    #[test]
+    fn test_summary() {
+        let runs = vec![
+            Run { day: 1, part: 1, answer: Ok(Answer::Integer(42)), elapsed: Duration::from_millis(2) },
+            Run { day: 8, part: 2, answer: Ok(Answer::Image(vec![vec![true, false]])), elapsed: Duration::from_millis(1) },
+            Run { day: 9, part: 1, answer: Err("No output".to_string()), elapsed: Duration::from_millis(0) },
+        ];
+        assert_eq!(summary(&runs),
+                   "day  part  answer                    time\n\
+                   \x20 1     1  42                    2.000 ms\n\
+                   \x20 8     2  (see above)           1.000 ms\n\
+                   \x20 9     1  error: No output      0.000 ms\n\
+                   total                            3.000 ms\n");
+    }
+}
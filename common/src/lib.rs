@@ -0,0 +1,160 @@
+use std::error;
+use std::fmt;
+
+pub type Result<T> = ::std::result::Result<T, Box<dyn error::Error>>;
+
+/// A puzzle answer, as it would be typed into the site.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Answer {
+    Integer(i64),
+    Text(String),
+    /// Rows of pixels, lit or not, that spell out the answer.
+    Image(Vec<Vec<bool>>),
+}
+
+impl fmt::Display for Answer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Answer::Integer(n) => write!(f, "{}", n),
+            Answer::Text(s) => write!(f, "{}", s),
+            Answer::Image(rows) => {
+                let rows: Vec<String> = rows
+                    .iter()
+                    .map(|row| row.iter().map(|lit| if *lit { '*' } else { ' ' }).collect())
+                    .collect();
+                write!(f, "{}", rows.join("\n"))
+            }
+        }
+    }
+}
+
+impl From<i64> for Answer {
+    fn from(n: i64) -> Answer {
+        Answer::Integer(n)
+    }
+}
+
+impl From<i32> for Answer {
+    fn from(n: i32) -> Answer {
+        Answer::Integer(n.into())
+    }
+}
+
+impl From<usize> for Answer {
+    fn from(n: usize) -> Answer {
+        Answer::Integer(n as i64)
+    }
+}
+
+impl From<String> for Answer {
+    fn from(s: String) -> Answer {
+        Answer::Text(s)
+    }
+}
+
+/// Puzzle input that doesn't make sense, with the line it's on if that
+/// helps.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl ParseError {
+    pub fn new<M: ToString>(message: M) -> ParseError {
+        ParseError { line: None, message: message.to_string() }
+    }
+
+    /// An error on a line, counting from 1.
+    pub fn at<M: ToString>(line: usize, message: M) -> ParseError {
+        ParseError { line: Some(line), message: message.to_string() }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl error::Error for ParseError {}
+
+/// One day's puzzle: how to read its input, and both parts.
+pub trait Solution {
+    const DAY: u32;
+    type Input;
+
+    fn parse(input: &str) -> ::std::result::Result<Self::Input, ParseError>;
+    fn part1(input: &Self::Input) -> Result<Answer>;
+    fn part2(input: &Self::Input) -> Result<Answer>;
+}
+
+/// A `Solution` with its input type hidden, so the `aoc` runner can keep
+/// every day in one list.
+pub trait Puzzle {
+    fn day(&self) -> u32;
+    /// Parses the input and runs part 1 or 2 on it.
+    fn solve(&self, part: u32, input: &str) -> Result<Answer>;
+}
+
+impl<S: Solution> Puzzle for S {
+    fn day(&self) -> u32 {
+        S::DAY
+    }
+
+    fn solve(&self, part: u32, input: &str) -> Result<Answer> {
+        let input = S::parse(input)?;
+        match part {
+            1 => S::part1(&input),
+            2 => S::part2(&input),
+            _ => Err(format!("Day {} has no part {}", S::DAY, part).into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Sum;
+
+    impl Solution for Sum {
+        const DAY: u32 = 0;
+        type Input = Vec<i64>;
+
+        fn parse(input: &str) -> ::std::result::Result<Vec<i64>, ParseError> {
+            input
+                .lines()
+                .enumerate()
+                .map(|(i, line)| line.parse().map_err(|e| ParseError::at(i + 1, e)))
+                .collect()
+        }
+
+        fn part1(input: &Vec<i64>) -> Result<Answer> {
+            Ok(input.iter().sum::<i64>().into())
+        }
+
+        fn part2(input: &Vec<i64>) -> Result<Answer> {
+            Ok(Answer::Text(format!("{} numbers", input.len())))
+        }
+    }
+
+    #[test]
+    fn test_puzzle() {
+        let puzzle: &dyn Puzzle = &Sum;
+        assert_eq!(puzzle.solve(1, "1\n2\n3").unwrap(), Answer::Integer(6));
+        assert_eq!(puzzle.solve(2, "1\n2\n3").unwrap().to_string(), "3 numbers");
+        let error = puzzle.solve(1, "1\nx").unwrap_err();
+        assert_eq!(error.to_string(), "line 2: invalid digit found in string");
+        assert!(puzzle.solve(3, "1").is_err());
+    }
+
+    #[test]
+    fn test_image() {
+        let image = Answer::Image(vec![vec![true, false, true], vec![false, true, false]]);
+        assert_eq!(image.to_string(), "* *\n * ");
+    }
+}
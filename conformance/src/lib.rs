@@ -0,0 +1,460 @@
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+
+/// The minimum an Intcode implementation has to offer to be checked against
+/// the suite.
+pub trait Vm {
+    fn load(program: Vec<i64>) -> Self;
+    fn push_input(&mut self, value: i64);
+    /// Runs until the program halts or needs more input.
+    fn run(&mut self);
+    fn output(&self) -> Vec<i64>;
+    fn read(&self, address: usize) -> i64;
+    fn write(&mut self, address: usize, value: i64);
+}
+
+/// A program, how to set it up, and what it should leave behind.
+#[derive(Debug, Clone, Copy)]
+pub struct Case {
+    pub name: &'static str,
+    pub program: &'static str,
+    /// Memory cells overwritten before the run, e.g. day02's noun and verb.
+    pub patches: &'static [(usize, i64)],
+    pub input: &'static [i64],
+    /// The expected start of memory after the run; empty to skip the check.
+    pub memory: &'static [i64],
+    /// Individual memory cells to check after the run.
+    pub cells: &'static [(usize, i64)],
+    /// The expected output; `None` to skip the check.
+    pub output: Option<&'static [i64]>,
+}
+
+const CASE: Case = Case {
+    name: "",
+    program: "",
+    patches: &[],
+    input: &[],
+    memory: &[],
+    cells: &[],
+    output: None,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    pub case: &'static str,
+    pub message: String,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.case, self.message)
+    }
+}
+
+pub fn parse(program: &str) -> Vec<i64> {
+    program
+        .split(',')
+        .map(|s| {
+            s.trim()
+                .parse()
+                .expect("Malformed program in conformance case")
+        })
+        .collect()
+}
+
+/// Runs one case, turning a panic in the implementation into a failure.
+pub fn check<V: Vm>(case: &Case) -> Result<(), Failure> {
+    let fail = |message: String| Failure {
+        case: case.name,
+        message,
+    };
+    let vm = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut vm = V::load(parse(case.program));
+        for (address, value) in case.patches {
+            vm.write(*address, *value);
+        }
+        for value in case.input {
+            vm.push_input(*value);
+        }
+        vm.run();
+        vm
+    }))
+    .map_err(|e| {
+        let message = e
+            .downcast_ref::<String>()
+            .cloned()
+            .or_else(|| e.downcast_ref::<&str>().map(|s| s.to_string()))
+            .unwrap_or_default();
+        fail(format!("panicked: {}", message))
+    })?;
+
+    let memory: Vec<i64> = (0..case.memory.len()).map(|a| vm.read(a)).collect();
+    if memory != case.memory {
+        return Err(fail(format!(
+            "memory {:?}, expected {:?}",
+            memory, case.memory
+        )));
+    }
+    for (address, expected) in case.cells {
+        let actual = vm.read(*address);
+        if actual != *expected {
+            return Err(fail(format!(
+                "[{}] = {}, expected {}",
+                address, actual, expected
+            )));
+        }
+    }
+    if let Some(expected) = case.output {
+        let output = vm.output();
+        if output != expected {
+            return Err(fail(format!(
+                "output {:?}, expected {:?}",
+                output, expected
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Runs every case in the suite, returning the ones that failed.
+pub fn run_all<V: Vm>() -> Vec<Failure> {
+    CASES.iter().filter_map(|c| check::<V>(c).err()).collect()
+}
+
+pub const CASES: &[Case] = &[
+    // day02: add and multiply in position mode
+    Case {
+        name: "day02_example1",
+        program: "1,9,10,3,2,3,11,0,99,30,40,50",
+        memory: &[3500, 9, 10, 70, 2, 3, 11, 0, 99, 30, 40, 50],
+        ..CASE
+    },
+    Case {
+        name: "day02_example2",
+        program: "1,0,0,0,99",
+        memory: &[2, 0, 0, 0, 99],
+        ..CASE
+    },
+    Case {
+        name: "day02_example3",
+        program: "2,3,0,3,99",
+        memory: &[2, 3, 0, 6, 99],
+        ..CASE
+    },
+    Case {
+        name: "day02_example4",
+        program: "2,4,4,5,99,0",
+        memory: &[2, 4, 4, 5, 99, 9801],
+        ..CASE
+    },
+    Case {
+        name: "day02_example5",
+        program: "1,1,1,4,99,5,6,0,99",
+        memory: &[30, 1, 1, 4, 2, 5, 6, 0, 99],
+        ..CASE
+    },
+    Case {
+        name: "day02_part1",
+        program: include_str!("../../day02/data/input.txt"),
+        patches: &[(1, 12), (2, 2)],
+        cells: &[(0, 4484226)],
+        ..CASE
+    },
+    Case {
+        name: "day02_part2",
+        program: include_str!("../../day02/data/input.txt"),
+        patches: &[(1, 56), (2, 96)],
+        cells: &[(0, 19690720)],
+        ..CASE
+    },
+    // day05: input/output, immediate mode, comparisons and jumps
+    Case {
+        name: "day05_echo",
+        program: "3,0,4,0,99",
+        input: &[421],
+        output: Some(&[421]),
+        ..CASE
+    },
+    Case {
+        name: "day05_immediate",
+        program: "1002,4,3,4,33",
+        memory: &[1002, 4, 3, 4, 99],
+        ..CASE
+    },
+    Case {
+        name: "day05_negative",
+        program: "1101,100,-1,4,0",
+        memory: &[1101, 100, -1, 4, 99],
+        ..CASE
+    },
+    Case {
+        name: "day05_equals_position",
+        program: "3,9,8,9,10,9,4,9,99,-1,8",
+        input: &[8],
+        output: Some(&[1]),
+        ..CASE
+    },
+    Case {
+        name: "day05_less_than_position",
+        program: "3,9,7,9,10,9,4,9,99,-1,8",
+        input: &[7],
+        output: Some(&[1]),
+        ..CASE
+    },
+    Case {
+        name: "day05_equals_immediate",
+        program: "3,3,1108,-1,8,3,4,3,99",
+        input: &[7],
+        output: Some(&[0]),
+        ..CASE
+    },
+    Case {
+        name: "day05_less_than_immediate",
+        program: "3,3,1107,-1,8,3,4,3,99",
+        input: &[8],
+        output: Some(&[0]),
+        ..CASE
+    },
+    Case {
+        name: "day05_jump_position",
+        program: "3,12,6,12,15,1,13,14,13,4,13,99,-1,0,1,9",
+        input: &[0],
+        output: Some(&[0]),
+        ..CASE
+    },
+    Case {
+        name: "day05_jump_immediate",
+        program: "3,3,1105,-1,9,1101,0,0,12,4,12,99,1",
+        input: &[8],
+        output: Some(&[1]),
+        ..CASE
+    },
+    Case {
+        name: "day05_compare_below",
+        program: "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,\
+                  125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99",
+        input: &[7],
+        output: Some(&[999]),
+        ..CASE
+    },
+    Case {
+        name: "day05_compare_equal",
+        program: "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,\
+                  125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99",
+        input: &[8],
+        output: Some(&[1000]),
+        ..CASE
+    },
+    Case {
+        name: "day05_compare_above",
+        program: "3,21,1008,21,8,20,1005,20,22,107,8,21,20,1006,20,31,1106,0,36,98,0,0,1002,21,\
+                  125,20,4,20,1105,1,46,104,999,1105,1,46,1101,1000,1,20,4,20,1105,1,46,98,99",
+        input: &[9],
+        output: Some(&[1001]),
+        ..CASE
+    },
+    Case {
+        name: "day05_part1",
+        program: include_str!("../../day05/data/input.txt"),
+        input: &[1],
+        output: Some(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 15314507]),
+        ..CASE
+    },
+    Case {
+        name: "day05_part2",
+        program: include_str!("../../day05/data/input.txt"),
+        input: &[5],
+        output: Some(&[652726]),
+        ..CASE
+    },
+    // day09: relative mode, large numbers and memory beyond the program
+    Case {
+        name: "day09_quine",
+        program: "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99",
+        output: Some(&[
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ]),
+        ..CASE
+    },
+    Case {
+        name: "day09_large_multiply",
+        program: "1102,34915192,34915192,7,4,7,99,0",
+        output: Some(&[1219070632396864]),
+        ..CASE
+    },
+    Case {
+        name: "day09_large_output",
+        program: "104,1125899906842624,99",
+        output: Some(&[1125899906842624]),
+        ..CASE
+    },
+    Case {
+        name: "relative_base_negative",
+        program: "109,-1,4,1,99",
+        output: Some(&[-1]),
+        ..CASE
+    },
+    Case {
+        name: "relative_read",
+        program: "109,-1,204,1,99",
+        output: Some(&[109]),
+        ..CASE
+    },
+    Case {
+        name: "relative_base_position",
+        program: "109,1,9,2,204,-6,99",
+        output: Some(&[204]),
+        ..CASE
+    },
+    Case {
+        name: "relative_base_immediate",
+        program: "109,1,109,9,204,-6,99",
+        output: Some(&[204]),
+        ..CASE
+    },
+    Case {
+        name: "relative_base_relative",
+        program: "109,1,209,-1,204,-106,99",
+        output: Some(&[204]),
+        ..CASE
+    },
+    Case {
+        name: "relative_input_position",
+        program: "109,1,3,3,204,2,99",
+        input: &[555],
+        output: Some(&[555]),
+        ..CASE
+    },
+    Case {
+        name: "relative_input_relative",
+        program: "109,1,203,2,204,2,99",
+        input: &[555],
+        output: Some(&[555]),
+        ..CASE
+    },
+    Case {
+        name: "relative_write_add",
+        program: "109,10,21101,2,3,0,204,0,99",
+        cells: &[(10, 5)],
+        output: Some(&[5]),
+        ..CASE
+    },
+    Case {
+        name: "relative_write_multiply",
+        program: "109,10,21102,4,5,1,204,1,99",
+        cells: &[(11, 20)],
+        output: Some(&[20]),
+        ..CASE
+    },
+    Case {
+        name: "relative_write_compare",
+        program: "109,20,21107,1,2,0,21108,3,3,1,22201,0,1,2,204,2,99",
+        cells: &[(20, 1), (21, 1), (22, 2)],
+        output: Some(&[2]),
+        ..CASE
+    },
+    Case {
+        name: "relative_jump_if_true",
+        program: "109,12,2205,0,1,104,1,99,104,2,99,0,1,8",
+        output: Some(&[2]),
+        ..CASE
+    },
+    Case {
+        name: "relative_jump_if_false",
+        program: "109,12,2206,0,1,104,1,99,104,2,99,0,1,8",
+        output: Some(&[1]),
+        ..CASE
+    },
+    Case {
+        name: "memory_far_past_program",
+        program: "1101,7,8,100000,4,100000,1001,100000,1,200000,4,200000,4,300000,109,150000,204,0,99",
+        cells: &[(100000, 15), (200000, 16), (300000, 0)],
+        output: Some(&[15, 16, 0, 0]),
+        ..CASE
+    },
+    Case {
+        name: "day09_part1",
+        program: include_str!("../../day09/data/input.txt"),
+        input: &[1],
+        output: Some(&[2752191671]),
+        ..CASE
+    },
+    Case {
+        name: "day09_part2",
+        program: include_str!("../../day09/data/input.txt"),
+        input: &[2],
+        output: Some(&[87571]),
+        ..CASE
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Deliberately broken: no relative mode and addition that subtracts.
+    struct Broken {
+        memory: Vec<i64>,
+        output: Vec<i64>,
+    }
+
+    impl Vm for Broken {
+        fn load(program: Vec<i64>) -> Broken {
+            Broken {
+                memory: program,
+                output: Vec::new(),
+            }
+        }
+
+        fn push_input(&mut self, _: i64) {}
+
+        fn run(&mut self) {
+            let m = &mut self.memory;
+            match m[0] {
+                1 => {
+                    let (x, y, a) = (m[1] as usize, m[2] as usize, m[3] as usize);
+                    m[a] = m[x] - m[y];
+                }
+                109 => panic!("Unknown opcode: 109"),
+                _ => (),
+            }
+        }
+
+        fn output(&self) -> Vec<i64> {
+            self.output.clone()
+        }
+
+        fn read(&self, address: usize) -> i64 {
+            self.memory.get(address).cloned().unwrap_or(0)
+        }
+
+        fn write(&mut self, address: usize, value: i64) {
+            self.memory[address] = value;
+        }
+    }
+
+    #[test]
+    fn test_names_unique() {
+        let mut names: Vec<&str> = CASES.iter().map(|c| c.name).collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), CASES.len());
+    }
+
+    #[test]
+    fn test_failures() {
+        assert_eq!(
+            check::<Broken>(&CASES[1]),
+            Err(Failure {
+                case: "day02_example2",
+                message: "memory [0, 0, 0, 0, 99], expected [2, 0, 0, 0, 99]".to_string()
+            })
+        );
+        let failure = check::<Broken>(CASES.iter().find(|c| c.name == "relative_read").unwrap());
+        assert_eq!(
+            failure.unwrap_err().message,
+            "panicked: Unknown opcode: 109"
+        );
+        assert_eq!(run_all::<Broken>().len(), CASES.len());
+    }
+}
@@ -0,0 +1,65 @@
+use common::{Answer, ParseError, Result, Solution};
+
+pub struct Day01;
+
+impl Solution for Day01 {
+    const DAY: u32 = 1;
+    // Module masses
+    type Input = Vec<i32>;
+
+    fn parse(input: &str) -> ::std::result::Result<Vec<i32>, ParseError> {
+        input
+            .lines()
+            .enumerate()
+            .map(|(i, line)| line.trim().parse().map_err(|e| ParseError::at(i + 1, e)))
+            .collect()
+    }
+
+    fn part1(masses: &Vec<i32>) -> Result<Answer> {
+        Ok(masses.iter().map(|m| calc_fuel(*m)).sum::<i32>().into())
+    }
+
+    fn part2(masses: &Vec<i32>) -> Result<Answer> {
+        Ok(masses.iter().map(|m| calc_fuel2(*m)).sum::<i32>().into())
+    }
+}
+
+fn calc_fuel(mass: i32) -> i32 {
+    let f = mass / 3 - 2;
+    if f > 0 { f } else { 0 }
+}
+
+fn calc_fuel2(mass: i32) -> i32 {
+    let mut fuel = calc_fuel(mass);
+    if fuel > 0 {
+        fuel += calc_fuel2(fuel);
+    }
+    fuel
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_part1() {
+        assert_eq!(calc_fuel(12), 2);
+        assert_eq!(calc_fuel(14), 2);
+        assert_eq!(calc_fuel(1969), 654);
+        assert_eq!(calc_fuel(100756), 33583);
+    }
+
+    #[test]
+    fn test_part2() {
+        assert_eq!(calc_fuel2(14), 2);
+        assert_eq!(calc_fuel2(1969), 966);
+        assert_eq!(calc_fuel2(100756), 50346);
+    }
+
+    #[test]
+    fn test_parse() {
+        let masses = Day01::parse("12\n14\n").unwrap();
+        assert_eq!(Day01::part1(&masses).unwrap(), Answer::Integer(4));
+        assert_eq!(Day01::parse("12\nx").unwrap_err().to_string(), "line 2: invalid digit found in string");
+    }
+}
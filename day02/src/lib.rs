@@ -0,0 +1,177 @@
+use common::{Answer, ParseError, Solution};
+use intcode::options::Options;
+use intcode::patch::{Patch, Patcher, Strategy};
+use intcode::{parse_instructions, Status};
+use std::error;
+use std::fmt;
+
+const GOAL: i64 = 19690720;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    Intcode(intcode::Error),
+    // The program stopped to read input, which day02 programs never do
+    AwaitingInput,
+    // Too few cells to hold a noun and verb
+    TooShort(usize),
+    NoSolution,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Intcode(e) => write!(f, "{}", e),
+            Error::AwaitingInput => write!(f, "Program is waiting for input"),
+            Error::TooShort(len) => write!(f, "Program has {} cells, too few for a noun and verb", len),
+            Error::NoSolution => write!(f, "No noun and verb give {}", GOAL),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl From<intcode::Error> for Error {
+    fn from(e: intcode::Error) -> Error {
+        Error::Intcode(e)
+    }
+}
+
+pub struct Day02;
+
+impl Solution for Day02 {
+    const DAY: u32 = 2;
+    type Input = Vec<i64>;
+
+    fn parse(input: &str) -> Result<Vec<i64>, ParseError> {
+        parse_instructions(input).map_err(|e| ParseError::new(format!("Bad program: {}", e)))
+    }
+
+    fn part1(program: &Vec<i64>) -> common::Result<Answer> {
+        Ok(part1(program, &Options::default())?.into())
+    }
+
+    fn part2(program: &Vec<i64>) -> common::Result<Answer> {
+        Ok(part2(program, &Options::default())?.into())
+    }
+}
+
+// The value left at address 0 with noun 12 and verb 2
+pub fn part1(program: &[i64], options: &Options) -> Result<i64, Error> {
+    check_length(program)?;
+    let mut copy = program.to_vec();
+
+    copy[1] = 12;
+    copy[2] = 2;
+
+    Ok(run(&copy, options)?[0])
+}
+
+// 100 * noun + verb for the noun and verb that leave GOAL at address 0.
+// The puzzle's programs only grow with the noun, so a binary search over it
+// usually finds them; other programs get every pair tried.
+pub fn part2(program: &[i64], options: &Options) -> Result<i64, Error> {
+    check_length(program)?;
+    let patches = vec![Patch::new(1, 0..=99), Patch::new(2, 0..=99)];
+    let patcher = Patcher::new(program, patches, 0, GOAL, options);
+    let solutions = match patcher.solve(Strategy::Monotonic) {
+        Ok(solutions) if !solutions.is_empty() => solutions,
+        _ => patcher.solve(Strategy::Exhaustive)?,
+    };
+    match solutions.first() {
+        Some(solution) => Ok(100 * solution[0] + solution[1]),
+        None => Err(Error::NoSolution),
+    }
+}
+
+// The noun and verb go in cells 1 and 2
+fn check_length(program: &[i64]) -> Result<(), Error> {
+    if program.len() < 3 {
+        return Err(Error::TooShort(program.len()));
+    }
+    Ok(())
+}
+
+// Runs the program to completion on the selected engine, returning the
+// cells it was loaded into
+fn run(program: &[i64], options: &Options) -> Result<Vec<i64>, Error> {
+    let mut machine = options.machine(program);
+    let status = machine.run()?;
+    options.collect(machine.as_ref());
+    match status {
+        Status::Halted => Ok((0..program.len()).map(|a| machine.read(a)).collect()),
+        _ => Err(Error::AwaitingInput),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use intcode::machine::Engine;
+    use std::fs;
+
+    #[test]
+    fn test1() {
+        let instructions = vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50];
+        let expected = vec![3500, 9, 10, 70, 2, 3, 11, 0, 99, 30, 40, 50];
+        assert_eq!(run(&instructions, &Options::default()), Ok(expected));
+    }
+
+    #[test]
+    fn test2() {
+        let instructions = vec![1, 0, 0, 0, 99];
+        let expected = vec![2, 0, 0, 0, 99];
+        assert_eq!(run(&instructions, &Options::default()), Ok(expected));
+    }
+
+    #[test]
+    fn test3() {
+        let instructions = vec![2, 3, 0, 3, 99];
+        let expected = vec![2, 3, 0, 6, 99];
+        assert_eq!(run(&instructions, &Options::default()), Ok(expected));
+    }
+
+    #[test]
+    fn test4() {
+        let instructions = vec![2, 4, 4, 5, 99, 0];
+        let expected = vec![2, 4, 4, 5, 99, 9801];
+        assert_eq!(run(&instructions, &Options::default()), Ok(expected));
+    }
+
+    #[test]
+    fn test5() {
+        let instructions = vec![1, 1, 1, 4, 99, 5, 6, 0, 99];
+        let expected = vec![30, 1, 1, 4, 2, 5, 6, 0, 99];
+        assert_eq!(run(&instructions, &Options::default()), Ok(expected));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(run(&[42, 0, 0, 0], &Options::default()), Err(Error::Intcode(intcode::Error::UnknownOpcode { opcode: 42, ip: 0 })));
+        assert_eq!(run(&[3, 0, 99], &Options::default()), Err(Error::AwaitingInput));
+        assert_eq!(part2(&[1, 0, 0, 0, 99], &Options::default()), Err(Error::NoSolution));
+        assert_eq!(part2(&[1, 0, 0, 0, 42], &Options::default()),
+                   Err(Error::Intcode(intcode::Error::UnknownOpcode { opcode: 42, ip: 4 })));
+        // [0] = [noun] + [verb], which doesn't grow with the noun
+        assert_eq!(part2(&[1, 0, 0, 0, 99, GOAL, 0], &Options::default()), Ok(305));
+        assert_eq!(part1(&[1, 0], &Options::default()), Err(Error::TooShort(2)));
+        assert_eq!(part2(&[99], &Options::default()).unwrap_err().to_string(),
+                   "Program has 1 cells, too few for a noun and verb");
+        assert_eq!(Day02::parse("1,x").unwrap_err().to_string(), "Bad program: invalid digit found in string");
+    }
+
+    #[test]
+    fn test_input() {
+        let input = fs::read_to_string("data/input.txt").expect("Error reading file");
+        let program = Day02::parse(&input).unwrap();
+        for engine in Engine::ALL.iter() {
+            let options = Options::new(*engine);
+            assert_eq!(part1(&program, &options), Ok(4484226), "{}", engine);
+            assert_eq!(part2(&program, &options), Ok(5696), "{}", engine);
+        }
+
+        let options = Options::from_args(&["--stats".to_string()]).unwrap();
+        part1(&program, &options).unwrap();
+        options.finish("part1");
+        assert!(options.report().starts_with("{\"part1\": {\"instructions\": {\"add\": 27, \"multiply\": 12, \"halt\": 1}"));
+    }
+}
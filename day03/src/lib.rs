@@ -0,0 +1,164 @@
+use common::{Answer, ParseError, Result, Solution};
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+
+pub struct Day03;
+
+impl Solution for Day03 {
+    const DAY: u32 = 3;
+    type Input = Wires;
+
+    fn parse(input: &str) -> ::std::result::Result<Self::Input, ParseError> {
+        let lines = input.lines().collect::<Vec<_>>();
+        if lines.len() < 2 {
+            return Err(ParseError::new("Expected two wires"));
+        }
+        Ok((read_wire(1, lines[0])?, read_wire(2, lines[1])?))
+    }
+
+    fn part1(wires: &Self::Input) -> Result<Answer> {
+        Ok(part1(wires).ok_or("The wires don't cross")?.into())
+    }
+
+    fn part2(wires: &Self::Input) -> Result<Answer> {
+        Ok(part2(wires).ok_or("The wires don't cross")?.into())
+    }
+}
+
+/// A direction, one of `UDLR`, and a length.
+pub type Segment = (char, i32);
+pub type Wires = (Vec<Segment>, Vec<Segment>);
+
+#[derive(Clone, Eq)]
+struct Point {
+    x: i32,
+    y: i32,
+    step: i32,
+}
+
+impl Point {
+    fn manhattan(&self) -> i32 {
+        self.x.abs() + self.y.abs()
+    }
+}
+
+impl Hash for Point {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.x.hash(state);
+        self.y.hash(state);
+    }
+}
+
+impl PartialEq for Point {
+    fn eq(&self, other: &Self) -> bool {
+        self.x == other.x && self.y == other.y
+    }
+}
+
+fn read_wire(line: usize, wire: &str) -> ::std::result::Result<Vec<Segment>, ParseError> {
+    wire.trim()
+        .split(',')
+        .map(|segment| {
+            let mut chars = segment.chars();
+            match chars.next() {
+                Some(d) if "UDLR".contains(d) => chars
+                    .as_str()
+                    .parse()
+                    .map(|length| (d, length))
+                    .map_err(|e| ParseError::at(line, format!("{}: {}", segment, e))),
+                _ => Err(ParseError::at(line, format!("{}: unknown direction", segment))),
+            }
+        })
+        .collect()
+}
+
+fn trace(segments: &[Segment]) -> HashSet<Point> {
+    let mut x = 0;
+    let mut y = 0;
+    let mut grid = HashSet::new();
+    let mut step = 0;
+    for &(direction, length) in segments {
+        let mut dx: i32 = 0;
+        let mut dy: i32 = 0;
+        match direction {
+            'U' => dy = 1,
+            'D' => dy = -1,
+            'L' => dx = -1,
+            _ => dx = 1,
+        }
+        for _ in 0..length {
+            x += dx;
+            y += dy;
+            step += 1;
+            grid.insert(Point { x, y, step });
+        }
+    }
+    grid
+}
+
+fn part1((wire1, wire2): &Wires) -> Option<i32> {
+    let w1 = trace(wire1);
+    let w2 = trace(wire2);
+
+    w1.intersection(&w2).map(|p| p.manhattan()).min()
+}
+
+fn part2((wire1, wire2): &Wires) -> Option<i32> {
+    let w1 = trace(wire1);
+    let w2 = trace(wire2);
+
+    w1.intersection(&w2)
+        .collect::<Vec<_>>()
+        .iter()
+        .cloned()
+        .map(|p| {
+            let p1 = w1.get(p).unwrap();
+            let p2 = w2.get(p).unwrap();
+            p1.step + p2.step
+        })
+        .min()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solve(part: fn(&Wires) -> Option<i32>, input: &str) -> i32 {
+        part(&Day03::parse(input).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test1() {
+        let input = "R75,D30,R83,U83,L12,D49,R71,U7,L72\nU62,R66,U55,R34,D71,R55,D58,R83";
+        assert_eq!(solve(part1, input), 159);
+    }
+
+    #[test]
+    fn test1_2() {
+        let input =
+            "R98,U47,R26,D63,R33,U87,L62,D20,R33,U53,R51\nU98,R91,D20,R16,D67,R40,U7,R15,U6,R7";
+        assert_eq!(solve(part1, input), 135);
+    }
+
+    #[test]
+    fn test2_1() {
+        let input = "R75,D30,R83,U83,L12,D49,R71,U7,L72\nU62,R66,U55,R34,D71,R55,D58,R83";
+        assert_eq!(solve(part2, input), 610);
+    }
+
+    #[test]
+    fn test2_2() {
+        let input =
+            "R98,U47,R26,D63,R33,U87,L62,D20,R33,U53,R51\nU98,R91,D20,R16,D67,R40,U7,R15,U6,R7";
+        assert_eq!(solve(part2, input), 410);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Day03::parse("R8,U5\nU7,L6").unwrap(), (vec![('R', 8), ('U', 5)], vec![('U', 7), ('L', 6)]));
+        assert_eq!(Day03::parse("R8,X5\nU7").unwrap_err().to_string(), "line 1: X5: unknown direction");
+        assert_eq!(Day03::parse("R8\nUx").unwrap_err().to_string(), "line 2: Ux: invalid digit found in string");
+        assert!(Day03::parse("R8").is_err());
+        assert!(Day03::part1(&Day03::parse("R1\nL1").unwrap()).is_err());
+    }
+}
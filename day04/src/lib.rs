@@ -0,0 +1,121 @@
+use common::{Answer, ParseError, Result, Solution};
+use std::ops::RangeInclusive;
+
+const MIN: i32 = 136760;
+const MAX: i32 = 595730;
+
+// The input is a range like 136760-595730; without one the puzzle range
+// built in here is used
+pub struct Day04;
+
+impl Solution for Day04 {
+    const DAY: u32 = 4;
+    type Input = RangeInclusive<i32>;
+
+    fn parse(input: &str) -> ::std::result::Result<Self::Input, ParseError> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Ok(MIN..=MAX);
+        }
+        let mut bounds = input.splitn(2, '-').map(|n| match n.parse::<i32>() {
+            Ok(n) if (100000..=999999).contains(&n) => Ok(n),
+            Ok(n) => Err(ParseError::at(1, format!("{} isn't six digits", n))),
+            Err(e) => Err(ParseError::at(1, format!("{}: {}", n, e))),
+        });
+        match (bounds.next(), bounds.next()) {
+            (Some(min), Some(max)) => Ok(min?..=max?),
+            _ => Err(ParseError::at(1, "Expected a range like 136760-595730")),
+        }
+    }
+
+    fn part1(range: &Self::Input) -> Result<Answer> {
+        Ok(part1(range.clone()).into())
+    }
+
+    fn part2(range: &Self::Input) -> Result<Answer> {
+        Ok(part2(range.clone()).into())
+    }
+}
+
+fn check_incrementing(digits: &[u8]) -> bool {
+    (1..=5).all(|i| digits[i] >= digits[i - 1])
+}
+
+fn check_adjacent(digits: &[u8]) -> bool {
+    (0..5).any(|i| digits[i] == digits[i + 1])
+}
+
+fn check_exactly_two_adjacent(digits: &[u8]) -> bool {
+    (0..5).any(|i| match i {
+        0 => digits[0] == digits[1] && digits[0] != digits[2],
+        4 => digits[4] == digits[5] && digits[4] != digits[3],
+        n => {
+            digits[n] == digits[n + 1] && (digits[n] != digits[n - 1] && digits[n] != digits[n + 2])
+        }
+    })
+}
+
+fn part1(range: RangeInclusive<i32>) -> i32 {
+    let mut count = 0;
+
+    for i in range {
+        let string = i.to_string(); // FIXME doing this to avoid "temporary value is freed at the end of this statement"
+        let digits = string.as_bytes();
+        if check_incrementing(digits) && check_adjacent(digits) {
+            count += 1;
+        }
+    }
+    count
+}
+
+fn part2(range: RangeInclusive<i32>) -> i32 {
+    let mut count = 0;
+
+    for i in range {
+        let string = i.to_string(); // FIXME doing this to avoid "temporary value is freed at the end of this statement"
+        let digits = string.as_bytes();
+        if check_incrementing(digits) && check_exactly_two_adjacent(digits) {
+            count += 1;
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_incrementing() {
+        assert!(check_incrementing(&[1, 1, 2, 2, 3, 3]));
+        assert!(!check_incrementing(&[1, 1, 2, 2, 3, 2]));
+    }
+
+    #[test]
+    fn test_check_adjacent() {
+        assert!(check_adjacent(&[1, 1, 2, 2, 3, 3]));
+        assert!(check_adjacent(&[1, 1, 1, 2, 3, 3]));
+        assert!(!check_adjacent(&[1, 2, 3, 4, 5, 6]));
+    }
+
+    #[test]
+    fn test_check_exactly_two_adjacent() {
+        assert!(check_exactly_two_adjacent(&[1, 1, 2, 2, 3, 3]));
+        assert!(!check_exactly_two_adjacent(&[1, 1, 1, 2, 3, 4]));
+        assert!(!check_exactly_two_adjacent(&[1, 2, 3, 4, 5, 6]));
+    }
+
+    // The part 2 rules for one password
+    fn check(password: i32) -> bool {
+        let string = password.to_string();
+        let digits = string.as_bytes();
+        check_incrementing(digits) && check_exactly_two_adjacent(digits)
+    }
+
+    #[test]
+    fn test_check() {
+        assert!(check(112233));
+        assert!(check(111122));
+        assert!(!check(123444));
+    }
+}
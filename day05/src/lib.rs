@@ -0,0 +1,188 @@
+use common::{Answer, ParseError, Solution};
+use intcode::machine::Machine;
+use intcode::options::Options;
+use intcode::Status;
+use std::error;
+use std::fmt;
+
+pub struct Day05;
+
+impl Solution for Day05 {
+    const DAY: u32 = 5;
+    type Input = Vec<i64>;
+
+    fn parse(input: &str) -> Result<Vec<i64>, ParseError> {
+        intcode::parse_instructions(input).map_err(|e| ParseError::new(format!("Bad program: {}", e)))
+    }
+
+    fn part1(instructions: &Vec<i64>) -> common::Result<Answer> {
+        Ok(diagnose(instructions, 1, &Options::default())?.into())
+    }
+
+    fn part2(instructions: &Vec<i64>) -> common::Result<Answer> {
+        Ok(diagnose(instructions, 5, &Options::default())?.into())
+    }
+}
+
+// Runs the program on the selected engine until it halts, returning the
+// machine and everything it output
+fn run(instructions: &[i64], inputs: &[i64], options: &Options)
+       -> Result<(Box<dyn Machine + Send + Sync>, Vec<i64>), DiagnosticError> {
+    let mut machine = options.machine(instructions);
+    inputs.iter().for_each(|v| machine.push_input(*v));
+    let status = machine.run().map_err(DiagnosticError::Intcode)?;
+    options.collect(machine.as_ref());
+    match status {
+        Status::Halted => {
+            let output = machine.take_output();
+            Ok((machine, output))
+        }
+        _ => Err(DiagnosticError::AwaitingInput),
+    }
+}
+
+// Why the diagnostic program didn't give a code
+#[derive(Debug, PartialEq)]
+pub enum DiagnosticError {
+    // A bad instruction or address in the program
+    Intcode(intcode::Error),
+    // The program wanted more input than the system ID
+    AwaitingInput,
+    NoOutput,
+    // A check before the diagnostic code reported something other than 0
+    TestFailed { test: usize, output: i64 },
+}
+
+impl fmt::Display for DiagnosticError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiagnosticError::Intcode(e) => write!(f, "{}", e),
+            DiagnosticError::AwaitingInput => write!(f, "Ran out of input"),
+            DiagnosticError::NoOutput => write!(f, "No diagnostic code"),
+            DiagnosticError::TestFailed { test, output } =>
+                write!(f, "Test {} failed with output {}", test, output),
+        }
+    }
+}
+
+impl error::Error for DiagnosticError {}
+
+// Runs the diagnostic program for a system ID, checking that every test
+// passed and returning the diagnostic code it ends with
+pub fn diagnose(instructions: &[i64], system: i64, options: &Options) -> Result<i64, DiagnosticError> {
+    let (_, mut outputs) = run(instructions, &[system], options)?;
+    let code = outputs.pop().ok_or(DiagnosticError::NoOutput)?;
+    match outputs.iter().position(|output| *output != 0) {
+        Some(i) => Err(DiagnosticError::TestFailed { test: i + 1, output: outputs[i] }),
+        None => Ok(code),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use intcode::machine::Engine;
+
+    // The cells the program was loaded into, after running it
+    fn memory(instructions: &[i64]) -> Vec<i64> {
+        let (machine, _) = run(instructions, &[], &Options::default()).unwrap();
+        (0..instructions.len()).map(|a| machine.read(a)).collect()
+    }
+
+    #[test]
+    fn decode() {
+        let (opcode, modes) = intcode::Computer::decode(1);
+        assert_eq!(opcode, 1);
+        assert_eq!(modes, [0, 0, 0]);
+        let (opcode, modes) = intcode::Computer::decode(11102);
+        assert_eq!(opcode, 2);
+        assert_eq!(modes, [1, 1, 1]);
+    }
+
+    #[test]
+    fn test1() {
+        let instructions = vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50];
+        let expected = vec![3500, 9, 10, 70, 2, 3, 11, 0, 99, 30, 40, 50];
+        assert_eq!(memory(&instructions), expected);
+    }
+
+    #[test]
+    fn test2() {
+        let instructions = vec![1, 0, 0, 0, 99];
+        let expected = vec![2, 0, 0, 0, 99];
+        assert_eq!(memory(&instructions), expected);
+    }
+
+    #[test]
+    fn test3() {
+        let instructions = vec![2, 3, 0, 3, 99];
+        let expected = vec![2, 3, 0, 6, 99];
+        assert_eq!(memory(&instructions), expected);
+    }
+
+    #[test]
+    fn test4() {
+        let instructions = vec![2, 4, 4, 5, 99, 0];
+        let expected = vec![2, 4, 4, 5, 99, 9801];
+        assert_eq!(memory(&instructions), expected);
+    }
+
+    #[test]
+    fn test5() {
+        let instructions = vec![1, 1, 1, 4, 99, 5, 6, 0, 99];
+        let expected = vec![30, 1, 1, 4, 2, 5, 6, 0, 99];
+        assert_eq!(memory(&instructions), expected);
+    }
+
+    #[test]
+    fn test6() {
+        let instructions = vec![3, 0, 4, 0, 99];
+        assert_eq!(run(&instructions, &[421], &Options::default()).unwrap().1, vec![421]);
+    }
+
+    #[test]
+    fn test7() {
+        let instructions = vec![1002, 4, 3, 4, 33];
+        let expected = vec![1002, 4, 3, 4, 99];
+        assert_eq!(memory(&instructions), expected);
+    }
+
+    #[test]
+    fn test_missing_input() {
+        let error = run(&[3, 0, 99], &[], &Options::default()).err();
+        assert_eq!(error, Some(DiagnosticError::AwaitingInput));
+    }
+
+    #[test]
+    fn test_diagnose() {
+        let input = std::fs::read_to_string("data/input.txt").expect("Error reading file");
+        let instructions = Day05::parse(&input).unwrap();
+        for engine in Engine::ALL.iter() {
+            let options = Options::new(*engine);
+            assert_eq!(diagnose(&instructions, 1, &options), Ok(15314507), "{}", engine);
+            assert_eq!(diagnose(&instructions, 5, &options), Ok(652726), "{}", engine);
+        }
+        let options = Options::default();
+
+        let failing = vec![104, 0, 104, 3, 104, 7, 99];
+        let error = diagnose(&failing, 1, &options).unwrap_err();
+        assert_eq!(error, DiagnosticError::TestFailed { test: 2, output: 3 });
+        assert_eq!(error.to_string(), "Test 2 failed with output 3");
+        assert_eq!(diagnose(&[99], 1, &options), Err(DiagnosticError::NoOutput));
+        assert_eq!(diagnose(&[3, 0, 3, 0, 99], 1, &options), Err(DiagnosticError::AwaitingInput));
+
+        // Malformed programs: an unknown mode, a negative address and an
+        // unknown opcode
+        for engine in Engine::ALL.iter() {
+            let options = Options::new(*engine);
+            assert_eq!(diagnose(&[304, 0, 99], 1, &options),
+                       Err(DiagnosticError::Intcode(intcode::Error::UnknownMode { mode: 3, ip: 0 })), "{}", engine);
+            assert_eq!(diagnose(&[3, -1, 99], 1, &options),
+                       Err(DiagnosticError::Intcode(intcode::Error::InvalidAddress { address: -1, ip: 0 })));
+            let error = diagnose(&[104, 0, 42], 1, &options).unwrap_err();
+            assert_eq!(error.to_string(), "Unrecognised opcode: 42, ip=2");
+        }
+        assert_eq!(Day05::parse("3,0,x").unwrap_err().to_string(), "Bad program: invalid digit found in string");
+        assert_eq!(Day05::parse("3, 0,\n4,0 ,99\n"), Ok(vec![3, 0, 4, 0, 99]));
+    }
+}
@@ -0,0 +1,117 @@
+use common::{Answer, ParseError, Result, Solution};
+use std::collections::HashMap;
+
+pub struct Day06;
+
+impl Solution for Day06 {
+    const DAY: u32 = 6;
+    // Each object mapped to the one it orbits
+    type Input = HashMap<String, String>;
+
+    fn parse(input: &str) -> ::std::result::Result<Self::Input, ParseError> {
+        let mut result: HashMap<String, String> = HashMap::new();
+        for (i, line) in input.lines().enumerate() {
+            match line.trim().split(')').collect::<Vec<&str>>()[..] {
+                [centre, object] if !centre.is_empty() && !object.is_empty() => {
+                    result.insert(object.to_string(), centre.to_string());
+                }
+                _ => return Err(ParseError::at(i + 1, format!("Expected an orbit like A)B, got {:?}", line))),
+            }
+        }
+        Ok(result)
+    }
+
+    fn part1(orbits: &Self::Input) -> Result<Answer> {
+        Ok(count_all(orbits).into())
+    }
+
+    fn part2(orbits: &Self::Input) -> Result<Answer> {
+        Ok(transfers(orbits).ok_or("YOU and SAN don't orbit anything in common")?.into())
+    }
+}
+
+// Compute the path from a starting point back to the the universal centre of mass
+fn path(start: &str, orbits: &HashMap<String, String>) -> Vec<String> {
+    let mut current = start;
+    let mut path: Vec<String> = Vec::new();
+    while let Some(s) = orbits.get(current) {
+        current = s;
+        path.push(s.to_string());
+    }
+    path
+}
+
+fn count_all(orbits: &HashMap<String, String>) -> usize {
+    orbits
+        .keys()
+        .fold(0usize, | total, v | total + path(v, orbits).len())
+}
+
+// Find the first point where two paths intersect
+fn path_intersect<'a>(path1: &'a [String], path2: &[String]) -> Option<&'a String> {
+    path1.iter().find(|s| path2.contains(s))
+}
+
+// Count the transfers from the object YOU orbit to the one SAN orbits
+fn transfers(orbits: &HashMap<String, String>) -> Option<usize> {
+    let path1 = path("YOU", orbits);
+    let path2 = path("SAN", orbits);
+    let intersect = path_intersect(&path1, &path2)?;
+    let index1 = path1.iter().position(|o| o == intersect)?;
+    let index2 = path2.iter().position(|o| o == intersect)?;
+    Some(index1 + index2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts() {
+        let s = "COM)B
+B)C
+C)D
+D)E
+E)F
+B)G
+G)H
+D)I
+E)J
+J)K
+K)L";
+        let orbits = Day06::parse(s).unwrap();
+        assert_eq!(path("D", &orbits).len(), 3);
+        assert_eq!(path("L", &orbits).len(), 7);
+        assert_eq!(count_all(&orbits), 42);
+    }
+
+    #[test]
+    fn test_intersect() {
+        let s = "COM)B
+B)C
+C)D
+D)E
+E)F
+B)G
+G)H
+D)I
+E)J
+J)K
+K)L
+K)YOU
+I)SAN";
+        let orbits = Day06::parse(s).unwrap();
+        let path1 = path("YOU", &orbits);
+        let path2 = path("SAN", &orbits);
+        assert_eq!(path_intersect(&path1, &path2).unwrap(), "D");
+        assert_eq!(transfers(&orbits), Some(4));
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Day06::parse("COM)B\nB)C").unwrap().get("C").unwrap(), "B");
+        assert_eq!(Day06::parse("COM)B\nB-C").unwrap_err().line, Some(2));
+        assert!(Day06::parse("COM)").is_err());
+        assert!(Day06::part2(&Day06::parse("COM)YOU\nX)SAN").unwrap()).is_err());
+    }
+}
\ No newline at end of file
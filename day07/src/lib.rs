@@ -0,0 +1,585 @@
+use common::{Answer, ParseError, Solution};
+use intcode::machine::Machine;
+use intcode::options::Options;
+use intcode::search::{default_threads, find_best, find_best_prefix, Space};
+use intcode::Status;
+use std::collections::{HashMap, VecDeque};
+use std::error;
+use std::fmt;
+use std::sync::{mpsc, Mutex};
+use std::thread;
+
+pub struct Day07;
+
+impl Solution for Day07 {
+    const DAY: u32 = 7;
+    type Input = Vec<i64>;
+
+    fn parse(input: &str) -> Result<Vec<i64>, ParseError> {
+        intcode::parse_instructions(input).map_err(|e| ParseError::new(format!("Bad program: {}", e)))
+    }
+
+    fn part1(instructions: &Vec<i64>) -> common::Result<Answer> {
+        let chain = Chain::new((0..5).collect());
+        let (_, signal) = part1(instructions, &chain, &Options::default())?;
+        Ok(signal.into())
+    }
+
+    fn part2(instructions: &Vec<i64>) -> common::Result<Answer> {
+        let chain = Chain::new((5..10).collect());
+        let (_, signal) = part2(instructions, &chain, false, &Options::default())?;
+        Ok(signal.into())
+    }
+}
+
+/// How the amplifiers are set up: how many there are, the phase settings
+/// to search, the signal fed to the first one, and whether phases may be
+/// used more than once.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chain {
+    pub amplifiers: usize,
+    pub phases: Vec<i64>,
+    pub signal: i64,
+    pub repeat: bool,
+}
+
+impl Chain {
+    pub fn new(phases: Vec<i64>) -> Chain {
+        Chain { amplifiers: 5, phases, signal: 0, repeat: false }
+    }
+
+    /// Checks there's at least one way to set the phases: one amplifier or
+    /// more, and without `repeat` no more of them than there are phases.
+    pub fn check(&self) -> Result<(), String> {
+        if self.amplifiers == 0 {
+            return Err("Need at least one amplifier".to_string());
+        }
+        if self.phases.is_empty() {
+            return Err("No phases to try".to_string());
+        }
+        if !self.repeat && self.amplifiers > self.phases.len() {
+            return Err(format!("{} amplifiers need {} different phases, got {}: {}",
+                               self.amplifiers, self.amplifiers, self.phases.len(),
+                               format_phases(&self.phases)));
+        }
+        Ok(())
+    }
+
+    fn space(&self) -> Space {
+        if self.repeat {
+            Space::Product(vec![self.phases.clone(); self.amplifiers])
+        } else {
+            Space::Permutations(self.phases.clone(), self.amplifiers)
+        }
+    }
+}
+
+// The best phase sequence and its signal, or the error for the first phases
+// that failed. Each amplifier's output depends only on its phase and the
+// signal into it, so sequences that start the same share the runs for their
+// common prefix.
+pub fn part1(instructions: &[i64], chain: &Chain, options: &Options)
+         -> Result<(Vec<i64>, i64), FeedbackError> {
+    chain.check().map_err(FeedbackError::Chain)?;
+    let template = options.machine(instructions);
+    let errors = Mutex::new(Vec::new());
+    let root = (Vec::new(), chain.signal);
+    let best = find_best_prefix(&chain.space(), default_threads(), root, |(prefix, signal), phase| {
+        let mut phases = prefix.clone();
+        phases.push(phase);
+        let mut cpu = template.fork();
+        cpu.push_input(phase);
+        cpu.push_input(*signal);
+        let result = match cpu.run() {
+            Ok(_) => cpu.take_output().pop().ok_or(FeedbackError::NoSignal { phases: phases.clone() }),
+            Err(error) => Err(FeedbackError::Machine { phases: phases.clone(), error }),
+        };
+        options.collect(cpu.as_ref());
+        result.map(|signal| (phases, signal)).map_err(|e| errors.lock().unwrap().push(e)).ok()
+    }, |(_, signal)| Some(*signal));
+    match errors.into_inner().unwrap().into_iter().min_by(|a, b| a.phases().cmp(b.phases())) {
+        Some(error) => Err(error),
+        None => best.ok_or_else(no_phases),
+    }
+}
+
+// Why the amplifiers gave no thruster signal
+#[derive(Debug, Clone, PartialEq)]
+pub enum FeedbackError {
+    // The chain has no phase settings to try, as `Chain::check` explains
+    Chain(String),
+    // An amplifier's program hit a bad instruction or address
+    Machine { phases: Vec<i64>, error: intcode::Error },
+    // Every machine still running is waiting for input nobody will send
+    Deadlock { phases: Vec<i64>, waiting: Vec<usize> },
+    // Everything halted without the last amplifier saying anything
+    NoSignal { phases: Vec<i64> },
+}
+
+impl FeedbackError {
+    fn phases(&self) -> &[i64] {
+        match self {
+            FeedbackError::Chain(_) => &[],
+            FeedbackError::Machine { phases, .. }
+            | FeedbackError::Deadlock { phases, .. }
+            | FeedbackError::NoSignal { phases } => phases,
+        }
+    }
+}
+
+impl fmt::Display for FeedbackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FeedbackError::Chain(e) => write!(f, "{}", e),
+            FeedbackError::Machine { phases, error } =>
+                write!(f, "Intcode error with phases {}: {}", format_phases(phases), error),
+            FeedbackError::Deadlock { phases, waiting } => {
+                let names: Vec<String> = waiting.iter().map(|i| amplifier_name(*i, phases.len())).collect();
+                write!(f, "Deadlock with phases {}: amplifiers {} waiting for input",
+                       format_phases(phases), names.join(", "))
+            }
+            FeedbackError::NoSignal { phases } =>
+                write!(f, "No thruster signal with phases {}", format_phases(phases)),
+        }
+    }
+}
+
+impl error::Error for FeedbackError {}
+
+fn no_phases() -> FeedbackError {
+    FeedbackError::Chain("No phases to try".to_string())
+}
+
+// The best phase sequence and its thruster signal, or the first error in
+// phase order
+pub fn part2(instructions: &[i64], chain: &Chain, threaded: bool, options: &Options)
+         -> Result<(Vec<i64>, i64), FeedbackError> {
+    chain.check().map_err(FeedbackError::Chain)?;
+    let errors = Mutex::new(Vec::new());
+    let best = find_best(&chain.space(), default_threads(), |phases| {
+        let result = if threaded {
+            feedback_threaded(instructions, phases, chain.signal, options)
+        } else {
+            feedback(instructions, phases, chain.signal, options, &mut |_, _| ())
+        };
+        result.map_err(|e| errors.lock().unwrap().push(e)).ok()
+    });
+    let mut errors: HashMap<Vec<i64>, FeedbackError> = errors
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|e| (e.phases().to_vec(), e))
+        .collect();
+    if errors.is_empty() {
+        return best.ok_or_else(no_phases);
+    }
+    let space = chain.space();
+    let first = (0..space.len()).map(|i| space.get(i)).find(|p| errors.contains_key(p)).unwrap();
+    Err(errors.remove(&first).unwrap())
+}
+
+// Runs the amplifiers round-robin, passing on everything each one outputs,
+// until all of them halt. Returns the last signal out of the last one, the
+// thruster signal. `on_signal` sees each signal with the amplifier it came
+// out of.
+fn feedback(instructions: &[i64], phases: &[i64], signal: i64, options: &Options,
+            on_signal: &mut dyn FnMut(usize, i64)) -> Result<i64, FeedbackError> {
+    let n = phases.len();
+    let mut cpus: Vec<Box<dyn Machine + Send + Sync>> = phases
+        .iter()
+        .map(|p| {
+            let mut cpu = options.machine(instructions);
+            cpu.push_input(*p);
+            cpu
+        })
+        .collect();
+    let mut status = vec![None; n];
+    let mut queues = vec![VecDeque::new(); n];
+    queues[0].push_back(signal);
+    let mut thrusters = None;
+
+    while status.iter().any(|s| *s != Some(Status::Halted)) {
+        let mut progress = false;
+        for i in 0..n {
+            let blocked = status[i] == Some(Status::AwaitingInput) && queues[i].is_empty();
+            if status[i] == Some(Status::Halted) || blocked {
+                continue;
+            }
+            progress = true;
+            queues[i].drain(..).for_each(|v| cpus[i].push_input(v));
+            let error = |error| FeedbackError::Machine { phases: phases.to_vec(), error };
+            status[i] = Some(cpus[i].run().map_err(error)?);
+            for value in cpus[i].take_output() {
+                on_signal(i, value);
+                if i == n - 1 {
+                    thrusters = Some(value);
+                }
+                queues[(i + 1) % n].push_back(value);
+            }
+        }
+        if !progress {
+            let waiting = (0..n).filter(|i| status[*i] != Some(Status::Halted)).collect();
+            return Err(FeedbackError::Deadlock { phases: phases.to_vec(), waiting });
+        }
+    }
+    cpus.iter().for_each(|cpu| options.collect(cpu.as_ref()));
+    thrusters.ok_or_else(|| FeedbackError::NoSignal { phases: phases.to_vec() })
+}
+
+// Who is waiting on whom in `feedback_threaded`. A channel can't say
+// whether anyone is blocked on it, so the threads keep count here: once
+// every machine still running is waiting and nothing is in flight, none of
+// them can ever go on.
+struct Ring {
+    running: Vec<bool>,
+    waiting: Vec<bool>,
+    in_flight: usize,
+    deadlock: bool,
+    // The first machine error, which stops the amplifier that hit it
+    error: Option<intcode::Error>,
+}
+
+impl Ring {
+    fn stuck(&self) -> bool {
+        self.in_flight == 0 && self.running.iter().zip(&self.waiting).all(|(r, w)| !r || *w)
+    }
+}
+
+// As `feedback`, with each amplifier on its own thread reading signals from
+// the one before it over a channel.
+fn feedback_threaded(instructions: &[i64], phases: &[i64], signal: i64, options: &Options)
+                     -> Result<i64, FeedbackError> {
+    let n = phases.len();
+    let (senders, receivers): (Vec<_>, Vec<_>) = (0..n).map(|_| mpsc::channel::<i64>()).unzip();
+    senders[0].send(signal).unwrap();
+    let ring = Mutex::new(Ring { running: vec![true; n], waiting: vec![false; n], in_flight: 1, deadlock: false,
+                                error: None });
+
+    let outputs = thread::scope(|s| {
+        let handles: Vec<_> = receivers
+            .into_iter()
+            .enumerate()
+            .map(|(i, input)| {
+                let output = senders[(i + 1) % n].clone();
+                let phase = phases[i];
+                let ring = &ring;
+                s.spawn(move || {
+                    let mut cpu = options.machine(instructions);
+                    cpu.push_input(phase);
+                    let mut sent = Vec::new();
+                    loop {
+                        let status = match cpu.run() {
+                            Ok(status) => status,
+                            Err(e) => {
+                                let mut ring = ring.lock().unwrap();
+                                ring.error.get_or_insert(e);
+                                ring.running[i] = false;
+                                break;
+                            }
+                        };
+                        {
+                            let mut ring = ring.lock().unwrap();
+                            for value in cpu.take_output() {
+                                sent.push(value);
+                                if output.send(value).is_ok() {
+                                    ring.in_flight += 1;
+                                }
+                            }
+                            if status == Status::Halted {
+                                // Anything still queued will never be read
+                                ring.running[i] = false;
+                                ring.in_flight -= input.try_iter().count();
+                                drop(input);
+                                ring.deadlock |= ring.running.contains(&true) && ring.stuck();
+                                break;
+                            }
+                            ring.waiting[i] = true;
+                            if ring.stuck() {
+                                ring.deadlock = true;
+                                break;
+                            }
+                        }
+                        // The thread before this one has gone once this fails
+                        match input.recv() {
+                            Ok(value) => {
+                                let mut ring = ring.lock().unwrap();
+                                ring.waiting[i] = false;
+                                ring.in_flight -= 1;
+                                cpu.push_input(value);
+                            }
+                            Err(_) => {
+                                ring.lock().unwrap().deadlock = true;
+                                break;
+                            }
+                        }
+                    }
+                    options.collect(cpu.as_ref());
+                    sent
+                })
+            })
+            .collect();
+        drop(senders);
+        handles.into_iter().map(|h| h.join().unwrap()).collect::<Vec<_>>()
+    });
+
+    let ring = ring.into_inner().unwrap();
+    if let Some(error) = ring.error {
+        return Err(FeedbackError::Machine { phases: phases.to_vec(), error });
+    }
+    if ring.deadlock {
+        let waiting = (0..n).filter(|i| ring.running[*i]).collect();
+        return Err(FeedbackError::Deadlock { phases: phases.to_vec(), waiting });
+    }
+    outputs[n - 1].last().cloned().ok_or_else(|| FeedbackError::NoSignal { phases: phases.to_vec() })
+}
+
+// Every signal out of an amplifier for one phase setting, as (loop,
+// amplifier, signal), where each signal out of the last amplifier, fed back
+// round to the first, ends a loop
+pub fn trace(instructions: &[i64], phases: &[i64], signal: i64, options: &Options)
+         -> Result<Vec<(usize, usize, i64)>, FeedbackError> {
+    let last = phases.len() - 1;
+    let mut round = 1;
+    let mut signals = Vec::new();
+    feedback(instructions, phases, signal, options, &mut |i, value| {
+        signals.push((round, i, value));
+        if i == last {
+            round += 1;
+        }
+    })?;
+    Ok(signals)
+}
+
+// Amplifier `i` of `n`, lettered A to Z while there are few enough
+pub fn amplifier_name(i: usize, n: usize) -> String {
+    if n <= 26 {
+        ((b'A' + i as u8) as char).to_string()
+    } else {
+        (i + 1).to_string()
+    }
+}
+
+// Every signal passed between amplifiers, one line each
+pub fn trace_csv(trace: &[(usize, usize, i64)], amplifiers: usize, signal: i64) -> String {
+    let mut csv = "loop,from,to,signal\n".to_string();
+    csv += &format!("0,input,{},{}\n", amplifier_name(0, amplifiers), signal);
+    for (i, from, signal) in trace {
+        csv += &format!("{},{},{},{}\n",
+                        i,
+                        amplifier_name(*from, amplifiers),
+                        amplifier_name((from + 1) % amplifiers, amplifiers),
+                        signal);
+    }
+    csv
+}
+
+// A bar per loop for the signal fed back round, on a log scale since it
+// tends to grow geometrically
+pub fn trace_chart(trace: &[(usize, usize, i64)], amplifiers: usize, width: usize) -> String {
+    let signals: Vec<i64> = trace
+        .iter()
+        .filter(|(_, from, _)| *from == amplifiers - 1)
+        .map(|(_, _, signal)| *signal)
+        .collect();
+    let scale = |signal: i64| (signal.max(0) as f64 + 1.0).log10();
+    let max = scale(signals.iter().cloned().max().unwrap_or(0)).max(1.0);
+    signals
+        .iter()
+        .enumerate()
+        .map(|(i, signal)| {
+            let bar = (scale(*signal) * width as f64 / max) as usize;
+            format!("{:>4} |{:<width$}| {}\n", i + 1, "#".repeat(bar), signal, width = width)
+        })
+        .collect()
+}
+
+// A phase set, as a range `0-4` or a list `0,1,2,3,4`
+pub fn parse_phases(s: &str) -> Result<Vec<i64>, String> {
+    let number = |n: &str| n.trim().parse::<i64>().map_err(|e| format!("{}: {}", n, e));
+    match s.find('-').filter(|i| *i > 0) {
+        Some(i) => Ok((number(&s[..i])?..=number(&s[i + 1..])?).collect()),
+        None => s.split(',').map(number).collect(),
+    }
+}
+
+pub fn format_phases(phases: &[i64]) -> String {
+    phases.iter().map(i64::to_string).collect::<Vec<_>>().join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use intcode::machine::Engine;
+
+    #[test]
+    fn test_part1_1() {
+        let instructions = Day07::parse("3,15,3,16,1002,16,10,16,1,16,15,\
+            15,4,15,99,0,0").unwrap();
+        for engine in Engine::ALL.iter() {
+            let chain = Chain::new((0..5).collect());
+            assert_eq!(part1(&instructions, &chain, &Options::new(*engine)).unwrap(),
+                       (vec![4, 3, 2, 1, 0], 43210));
+        }
+    }
+
+    #[test]
+    fn test_part1_2() {
+        let instructions = Day07::parse("3,23,3,24,1002,24,10,24,1002,23,\
+            -1,23,101,5,23,23,1,24,23,23,4,23,99,0,0").unwrap();
+        for engine in Engine::ALL.iter() {
+            let chain = Chain::new((0..5).collect());
+            assert_eq!(part1(&instructions, &chain, &Options::new(*engine)).unwrap(),
+                       (vec![0, 1, 2, 3, 4], 54321));
+        }
+    }
+
+    #[test]
+    fn test_part1_3() {
+        let instructions = Day07::parse("3,31,3,32,1002,32,10,32,1001,31,\
+            -2,31,1007,31,0,33,1002,33,7,33,1,33,31,31,1,32,31,31,4,31,99,0,0,0").unwrap();
+        for engine in Engine::ALL.iter() {
+            let chain = Chain::new((0..5).collect());
+            assert_eq!(part1(&instructions, &chain, &Options::new(*engine)).unwrap(),
+                       (vec![1, 0, 4, 3, 2], 65210));
+        }
+    }
+
+    #[test]
+    fn test_part2_1() {
+        let instructions = Day07::parse("3,26,1001,26,-4,26,3,27,1002,27,2,\
+        27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5").unwrap();
+        for engine in Engine::ALL.iter() {
+            let chain = Chain::new((5..10).collect());
+            for threaded in [false, true].iter() {
+                assert_eq!(part2(&instructions, &chain, *threaded, &Options::new(*engine)),
+                           Ok((vec![9, 8, 7, 6, 5], 139629729)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_part2_2() {
+        let instructions = Day07::parse("3,52,1001,52,-5,52,3,53,1,52,56,\
+        54,1007,54,5,55,1005,55,26,1001,54,-5,54,1105,1,12,1,53,54,53,1008,54,0,55,1001,55,1,55,\
+        2,53,55,53,4,53,1001,56,-1,56,1005,56,6,99,0,0,0,0,10").unwrap();
+        for engine in Engine::ALL.iter() {
+            let chain = Chain::new((5..10).collect());
+            for threaded in [false, true].iter() {
+                assert_eq!(part2(&instructions, &chain, *threaded, &Options::new(*engine)),
+                           Ok((vec![9, 7, 8, 5, 6], 18216)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_halt() {
+        // Each amplifier reads one signal, outputs it plus one and plus two,
+        // and halts with the second output still queued for the next one
+        let instructions = Day07::parse("3,20,3,21,1001,21,1,21,4,21,\
+            1001,21,1,21,4,21,99,0,0,0,0,0").unwrap();
+        let chain = Chain::new((5..10).collect());
+        for threaded in [false, true].iter() {
+            assert_eq!(part2(&instructions, &chain, *threaded, &Options::default()),
+                       Ok((vec![5, 6, 7, 8, 9], 6)));
+        }
+    }
+
+    #[test]
+    fn test_deadlock() {
+        // Reads a third input that never comes
+        let instructions = Day07::parse("3,9,3,9,3,9,99,0,0,0").unwrap();
+        let chain = Chain::new((5..10).collect());
+        for threaded in [false, true].iter() {
+            let error = part2(&instructions, &chain, *threaded, &Options::default()).unwrap_err();
+            assert_eq!(error, FeedbackError::Deadlock { phases: vec![5, 6, 7, 8, 9],
+                                                        waiting: vec![0, 1, 2, 3, 4] });
+            assert_eq!(error.to_string(),
+                       "Deadlock with phases 5,6,7,8,9: amplifiers A, B, C, D, E waiting for input");
+        }
+
+        // Halts without output, leaving the rest waiting
+        let instructions = Day07::parse("3,7,3,7,99,0,0,0").unwrap();
+        for threaded in [false, true].iter() {
+            let error = part2(&instructions, &chain, *threaded, &Options::default()).unwrap_err();
+            assert_eq!(error.to_string(),
+                       "Deadlock with phases 5,6,7,8,9: amplifiers B, C, D, E waiting for input");
+        }
+
+        // Halts after reading only the phase
+        let instructions = Day07::parse("3,3,99,0").unwrap();
+        for threaded in [false, true].iter() {
+            let error = part2(&instructions, &chain, *threaded, &Options::default()).unwrap_err();
+            assert_eq!(error, FeedbackError::NoSignal { phases: vec![5, 6, 7, 8, 9] });
+        }
+    }
+
+    #[test]
+    fn test_machine_error() {
+        // Reads the phase and the signal, then runs into a bad opcode
+        let instructions = Day07::parse("3,9,3,9,42,0,0,0,0,0").unwrap();
+        let error = FeedbackError::Machine { phases: vec![0],
+                                             error: intcode::Error::UnknownOpcode { opcode: 42, ip: 4 } };
+        let chain = Chain::new((0..5).collect());
+        assert_eq!(part1(&instructions, &chain, &Options::default()), Err(error));
+        let chain = Chain::new((5..10).collect());
+        for threaded in [false, true].iter() {
+            let error = part2(&instructions, &chain, *threaded, &Options::default()).unwrap_err();
+            assert_eq!(error.to_string(), "Intcode error with phases 5,6,7,8,9: Unrecognised opcode: 42, ip=4");
+        }
+        assert!(Day07::part1(&instructions).is_err());
+        assert!(Day07::part2(&instructions).is_err());
+    }
+
+    #[test]
+    fn test_trace() {
+        let instructions = Day07::parse("3,26,1001,26,-4,26,3,27,1002,27,2,\
+        27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5").unwrap();
+        let options = Options::default();
+        let trace = trace(&instructions, &[9, 8, 7, 6, 5], 0, &options).unwrap();
+        assert_eq!(trace.len(), 25);
+        assert_eq!(&trace[..5], [(1, 0, 5), (1, 1, 14), (1, 2, 31), (1, 3, 64), (1, 4, 129)]);
+        assert_eq!(trace[24], (5, 4, 139629729));
+
+        let csv = trace_csv(&trace, 5, 0);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2 + 25);
+        assert_eq!(&lines[..3], ["loop,from,to,signal", "0,input,A,0", "1,A,B,5"]);
+        assert_eq!(lines[26], "5,E,A,139629729");
+
+        let chart = trace_chart(&trace, 5, 10);
+        let lines: Vec<&str> = chart.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], "   1 |##        | 129");
+        assert_eq!(lines[4], "   5 |##########| 139629729");
+
+        // Each amplifier passes its signal on twice, so the first sends two
+        // signals in the first loop and the second closes two loops
+        let instructions = Day07::parse("3,9,3,9,4,9,4,9,99,0").unwrap();
+        assert_eq!(super::trace(&instructions, &[0, 1], 7, &options),
+                   Ok(vec![(1, 0, 7), (1, 0, 7), (1, 1, 7), (2, 1, 7)]));
+    }
+
+    #[test]
+    fn test_chain() {
+        // Outputs signal * 10 + phase, so the signal spells out the phases
+        let instructions = Day07::parse("3,15,3,16,1002,16,10,16,1,16,15,\
+            15,4,15,99,0,0").unwrap();
+        let options = Options::default();
+        let mut chain = Chain::new((0..5).collect());
+        chain.signal = 1;
+        assert_eq!(part1(&instructions, &chain, &options), Ok((vec![4, 3, 2, 1, 0], 143210)));
+        chain.repeat = true;
+        assert_eq!(part1(&instructions, &chain, &options), Ok((vec![4, 4, 4, 4, 4], 144444)));
+        chain.amplifiers = 3;
+        chain.phases = parse_phases("2-3").unwrap();
+        assert_eq!(part1(&instructions, &chain, &options), Ok((vec![3, 3, 3], 1333)));
+
+        chain.amplifiers = 0;
+        let error = Err(FeedbackError::Chain("Need at least one amplifier".to_string()));
+        assert_eq!(part1(&instructions, &chain, &options), error);
+        assert_eq!(part2(&instructions, &chain, false, &options), error);
+        chain.amplifiers = 3;
+        chain.phases.clear();
+        assert_eq!(part2(&instructions, &chain, true, &options).unwrap_err().to_string(), "No phases to try");
+    }
+}
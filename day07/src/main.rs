@@ -1,263 +1,216 @@
-extern crate itertools;
-
-use itertools::Itertools;
-use std::{fs, io};
-use std::str::FromStr;
+use common::Solution;
+use day07::{amplifier_name, format_phases, parse_phases, part1, part2, trace, trace_chart, trace_csv, Chain,
+             Day07};
+use intcode::machine::{Engine, Machine};
+use intcode::options::Options;
+use intcode::tui::Dashboard;
+use intcode::Computer;
+use std::env;
+use std::fs;
+use std::io;
+use std::process;
 use std::io::Read;
-
-struct Computer {
-    ip: usize,
-    memory: Vec<i32>,
-    memory_orig: Vec<i32>,
-    input: Vec<i32>,
-    output: Vec<i32>,
-    halted: bool
-}
-
-impl Computer {
-    fn new(memory: Vec<i32>) -> Computer {
-        Computer {
-            ip: 0,
-            memory: memory.clone(),
-            memory_orig: memory,
-            input: Vec::new(),
-            output: Vec::new(),
-            halted: false,
+use std::time::Instant;
+
+// Watches the feedback loop for one phase setting on a terminal dashboard.
+// The dashboard steps `Computer`s, so only the engines built on one will do.
+fn watch(instructions: &[i64], phases: &[i64], signal: i64, options: &Options) -> Result<Option<i64>, String> {
+    let checked = match options.engine {
+        Engine::Interpreter => false,
+        Engine::Checked => true,
+        engine => return Err(format!("The dashboard can't run the {} engine", engine)),
+    };
+    let mut dashboard = Dashboard::new();
+    for (i, phase) in phases.iter().enumerate() {
+        let mut cpu = Computer::new(instructions.to_vec());
+        cpu.checked = checked;
+        if options.stats {
+            cpu.collect_stats();
         }
-    }
-
-    fn reset(&mut self) {
-        self.ip = 0;
-        self.memory = self.memory_orig.clone();
-        self.input.clear();
-        self.output.clear();
-        self.halted = false;
-    }
-
-    fn push_input(&mut self, value: i32) {
-        self.input.push(value);
-    }
-
-    fn pop_output(&mut self) -> i32 {
-        self.output.remove(self.output.len() - 1)
-    }
-
-    fn peek(&self, offset: usize, mode: u8) -> i32 {
-        let value = match mode {
-            0 => self.memory[self.memory[offset] as usize] as i32,
-            1 => self.memory[offset] as i32,
-            m => panic!("Unknown mode: {}", m),
-        };
-        value
-    }
-
-    fn run(&mut self) {
-        loop {
-            let (opcode, modes) = Computer::decode(self.memory[self.ip] as i32);
-            match opcode {
-                1 => {
-                    // add
-                    let x = self.peek(self.ip + 1, modes[0]);
-                    let y = self.peek(self.ip + 2, modes[1]);
-                    let a = self.memory[self.ip + 3] as usize;
-                    self.memory[a] = x + y;
-                    self.ip += 4;
-                }
-                2 => {
-                    // multiply
-                    let x = self.peek(self.ip + 1, modes[0]);
-                    let y = self.peek(self.ip + 2, modes[1]);
-                    let a = self.memory[self.ip + 3] as usize;
-                    self.memory[a] = x * y;
-                    self.ip += 4;
-                }
-                3 => {
-                    // input
-                    let a = self.memory[self.ip + 1] as usize;
-                    if self.input.is_empty() {
-                        break;
-                    }
-                    self.memory[a] = self.input.remove(0);
-                    self.ip += 2;
-                }
-                4 => {
-                    // output
-                    let a = self.memory[self.ip + 1] as usize;
-                    self.output.push(self.memory[a]);
-                    self.ip += 2;
-                    break;
-                }
-                5 => {
-                    // jump if true
-                    let x = self.peek(self.ip + 1, modes[0]);
-                    let y = self.peek(self.ip + 2, modes[1]);
-                    if x != 0 {
-                        self.ip = y as usize;
-                    } else {
-                        self.ip += 3;
-                    }
-                }
-                6 => {
-                    // jump if false
-                    let x = self.peek(self.ip + 1, modes[0]);
-                    let y = self.peek(self.ip + 2, modes[1]);
-                    if x == 0 {
-                        self.ip = y as usize;
-                    } else {
-                        self.ip += 3;
-                    }
-                }
-                7 => {
-                    // less than
-                    let x = self.peek(self.ip + 1, modes[0]);
-                    let y = self.peek(self.ip + 2, modes[1]);
-                    let a = self.memory[self.ip + 3] as usize;
-                    if x < y {
-                        self.memory[a] = 1;
-                    } else {
-                        self.memory[a] = 0;
-                    }
-                    self.ip += 4;
-                }
-                8 => {
-                    // equals
-                    let x = self.peek(self.ip + 1, modes[0]);
-                    let y = self.peek(self.ip + 2, modes[1]);
-                    let a = self.memory[self.ip + 3] as usize;
-                    if x == y {
-                        self.memory[a] = 1;
-                    } else {
-                        self.memory[a] = 0;
-                    }
-                    self.ip += 4;
-                }
-                99 => {
-                    self.halted = true;
-                    break
-                }
-                opcode => panic!(format!("Unrecognised opcode: {}, ip={}", opcode, self.ip)),
-            }
+        cpu.push_input(*phase);
+        if i == 0 {
+            cpu.push_input(signal);
         }
+        dashboard.add(&format!("amp {} (phase {})", amplifier_name(i, phases.len()), phase), cpu);
     }
-
-    fn decode(instruction: i32) -> (i32, [u8; 3]) {
-        (
-            instruction % 100,
-            [
-                ((instruction / 100) % 10) as u8,
-                ((instruction / 1000) % 10) as u8,
-                ((instruction / 10000) % 10) as u8,
-            ],
-        )
+    for i in 0..phases.len() {
+        dashboard.connect(i, (i + 1) % phases.len());
     }
+    dashboard.run().map_err(|e| format!("/dev/tty: {}", e))?;
+    (0..phases.len()).for_each(|i| options.collect(dashboard.computer(i)));
+    Ok(dashboard.output(phases.len() - 1).pop())
 }
 
-fn parse_instructions(input: String) -> Vec<i32> {
-    input
-        .split(",")
-        .map(|s| i32::from_str(s).unwrap())
-        .collect()
+struct Config {
+    chain1: Chain,
+    chain2: Chain,
+    tui: Option<Vec<i64>>,
+    threaded: bool,
+    bench: bool,
+    trace: Option<String>,
 }
 
-fn part1(instructions: &Vec<i32>) -> i32 {
-    (0..5)
-        .permutations(5)
-        .map(|phases| {
-            let mut signal = 0;
-            let mut cpu = Computer::new(instructions.clone());
-
-            for c in 0..phases.len() {
-                cpu.input.push(phases[c]);
-                cpu.input.push(signal);
-                cpu.run();
-                signal = cpu.output.pop().expect("Expected output");
-                cpu.reset();
+fn parse_args(args: &[String]) -> Result<Config, String> {
+    let mut config = Config {
+        chain1: Chain::new((0..5).collect()),
+        chain2: Chain::new((5..10).collect()),
+        tui: None,
+        threaded: false,
+        bench: false,
+        trace: None,
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--amplifiers" => {
+                let n = value()?;
+                let n = n.parse::<usize>().map_err(|e| format!("{}: {}", n, e))?;
+                config.chain1.amplifiers = n;
+                config.chain2.amplifiers = n;
             }
-            signal
-        })
-        .max()
-        .unwrap()
-}
-
-fn part2(instructions: &Vec<i32>) -> i32 {
-    let mut cpus: Vec<Computer> = (0..5)
-        .map(|_| Computer::new(instructions.clone()))
-        .collect();
-
-    let mut max = 0;
-
-    (5..10)
-        .permutations(5)
-        .map(|phase| {
-            for i in 0..phase.len() {
-                cpus[i].reset();
-                cpus[i].input.push(phase[i]);
+            "--phases" => config.chain1.phases = parse_phases(value()?)?,
+            "--feedback-phases" => config.chain2.phases = parse_phases(value()?)?,
+            "--signal" => {
+                let n = value()?;
+                let n = n.parse::<i64>().map_err(|e| format!("{}: {}", n, e))?;
+                config.chain1.signal = n;
+                config.chain2.signal = n;
             }
-
-            let mut signal = 0;
-            'HALT: loop {
-                for i in 0..cpus.len() {
-                    cpus[i].push_input(signal);
-                    cpus[i].run();
-                    if cpus[i].halted { break 'HALT; }
-                    signal = cpus[i].pop_output();
-                }
-                max = std::cmp::max(max, signal);
+            "--repeat" => {
+                config.chain1.repeat = true;
+                config.chain2.repeat = true;
+            }
+            "--threaded" => config.threaded = true,
+            "--bench" => config.bench = true,
+            "--trace" => config.trace = Some(value()?.clone()),
+            "--tui" => {
+                let phases = args.next().ok_or("--tui needs phases, e.g. 9,8,7,6,5")?;
+                config.tui = Some(parse_phases(phases)?);
             }
-            signal
-        })
-        .max()
-        .unwrap();
-    max
+            // Read by `Options::from_args`
+            "--engine" => {
+                value()?;
+            }
+            "--stats" => (),
+            engine if engine.starts_with("--engine=") => (),
+            _ => return Err(format!("Unknown argument: {}", arg)),
+        }
+    }
+    config.chain1.check()?;
+    config.chain2.check()?;
+    Ok(config)
+}
+
+// Times part 2 round-robin and threaded, checking they agree
+fn bench(instructions: &[i64], chain: &Chain, options: &Options) {
+    let mut expected = None;
+    for threaded in [false, true].iter() {
+        let start = Instant::now();
+        let result = part2(instructions, chain, *threaded, options);
+        println!("{:<12} {:>10.3} ms  {}",
+                 if *threaded { "threaded" } else { "round-robin" },
+                 start.elapsed().as_secs_f64() * 1000.0,
+                 match &result {
+                     Ok((_, signal)) => signal.to_string(),
+                     Err(e) => e.to_string(),
+                 });
+        match &expected {
+            Some(expected) => assert_eq!(*expected, result, "threaded loop disagrees"),
+            None => expected = Some(result),
+        }
+    }
 }
 
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let options = Options::from_args(&args).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(2);
+    });
+    let config = parse_args(&args).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(2);
+    });
+
     let mut input = String::new();
     let _ = io::stdin().read_to_string(&mut input);
 
-    let instructions = parse_instructions(input);
-
-    println!("Part 1: {}", part1(&instructions));
-    println!("Part 2: {}", part2(&instructions));
-}
+    let instructions = Day07::parse(&input).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(2);
+    });
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_part1_1() {
-        let instructions = parse_instructions("3,15,3,16,1002,16,10,16,1,16,15,\
-            15,4,15,99,0,0".to_string());
-        assert_eq!(part1(&instructions), 43210);
+    if config.bench {
+        bench(&instructions, &config.chain2, &options);
+        return;
     }
-
-    #[test]
-    fn test_part1_2() {
-        let instructions = parse_instructions("3,23,3,24,1002,24,10,24,1002,23,\
-            -1,23,101,5,23,23,1,24,23,23,4,23,99,0,0".to_string());
-        assert_eq!(part1(&instructions), 54321);
-    }
-
-    #[test]
-    fn test_part1_3() {
-        let instructions = parse_instructions("3,31,3,32,1002,32,10,32,1001,31,\
-            -2,31,1007,31,0,33,1002,33,7,33,1,33,31,31,1,32,31,31,4,31,99,0,0,0".to_string());
-        assert_eq!(part1(&instructions), 65210);
+    if let Some(phases) = config.tui {
+        match watch(&instructions, &phases, config.chain2.signal, &options) {
+            Ok(Some(signal)) => println!("Signal: {}", signal),
+            Ok(None) => println!("No signal"),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        }
+        options.finish("tui");
+        options.print_report();
+        return;
     }
 
-    #[test]
-    fn test_part2_1() {
-        let instructions = parse_instructions("3,26,1001,26,-4,26,3,27,1002,27,2,\
-        27,1,27,26,27,4,27,1001,28,-1,28,1005,28,6,99,0,0,5".to_string());
-        assert_eq!(part2(&instructions), 139629729);
+    let (phases, signal) = part1(&instructions, &config.chain1, &options).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(1);
+    });
+    println!("Part 1: {}", signal);
+    println!("Part 1 phases: {}", format_phases(&phases));
+    options.finish("part1");
+    let (phases, signal) = part2(&instructions, &config.chain2, config.threaded, &options)
+        .unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        });
+    println!("Part 2: {}", signal);
+    println!("Part 2 phases: {}", format_phases(&phases));
+    options.finish("part2");
+    if let Some(path) = config.trace {
+        let chain = &config.chain2;
+        let trace = trace(&instructions, &phases, chain.signal, &Options::default()).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        });
+        fs::write(&path, trace_csv(&trace, chain.amplifiers, chain.signal)).unwrap_or_else(|e| {
+            eprintln!("{}: {}", path, e);
+            process::exit(1);
+        });
+        print!("{}", trace_chart(&trace, chain.amplifiers, 50));
     }
+    options.print_report();
+}
 
+#[cfg(test)]
+mod tests {
+    use super::*;
     #[test]
-    fn test_part2_2() {
-        let instructions = parse_instructions("3,52,1001,52,-5,52,3,53,1,52,56,\
-        54,1007,54,5,55,1005,55,26,1001,54,-5,54,1105,1,12,1,53,54,53,1008,54,0,55,1001,55,1,55,\
-        2,53,55,53,4,53,1001,56,-1,56,1005,56,6,99,0,0,0,0,10".to_string());
-        assert_eq!(part2(&instructions), 18216);
+    fn test_parse_args() {
+        let args: Vec<String> = "--amplifiers 3 --phases 1,2,7 --feedback-phases 5-7 --signal 9 --repeat"
+            .split(' ').map(String::from).collect();
+        let config = parse_args(&args).unwrap();
+        assert_eq!(config.chain1, Chain { amplifiers: 3, phases: vec![1, 2, 7], signal: 9, repeat: true });
+        assert_eq!(config.chain2.phases, vec![5, 6, 7]);
+        assert!(parse_args(&["--phases".to_string()]).is_err());
+        assert!(parse_args(&["--signal".to_string(), "x".to_string()]).is_err());
+
+        let args = |line: &str| line.split(' ').map(String::from).collect::<Vec<String>>();
+        assert_eq!(parse_args(&args("--amplifiers 0")).err(), Some("Need at least one amplifier".to_string()));
+        assert_eq!(parse_args(&args("--amplifiers 6")).err(),
+                   Some("6 amplifiers need 6 different phases, got 5: 0,1,2,3,4".to_string()));
+        assert!(parse_args(&args("--amplifiers 5")).is_ok());
+        assert!(parse_args(&args("--amplifiers 6 --repeat")).is_ok());
+
+        assert_eq!(parse_args(&args("--amplifer 3")).err(), Some("Unknown argument: --amplifer".to_string()));
+        assert!(parse_args(&args("--engine compiled --stats --engine=decoded --threaded")).is_ok());
+        assert!(parse_args(&args("--engine")).is_err());
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,118 @@
+use common::{Answer, ParseError, Result, Solution};
+
+const WIDTH: usize = 25;
+const HEIGHT: usize = 6;
+
+pub struct Day08;
+
+impl Solution for Day08 {
+    const DAY: u32 = 8;
+    // The image's layers, each a list of pixel digits
+    type Input = Vec<Vec<u8>>;
+
+    fn parse(input: &str) -> ::std::result::Result<Self::Input, ParseError> {
+        let input = input.trim();
+        if let Some(c) = input.chars().find(|c| !c.is_ascii_digit()) {
+            return Err(ParseError::at(1, format!("{:?} isn't a pixel", c)));
+        }
+        if input.is_empty() || !input.len().is_multiple_of(WIDTH * HEIGHT) {
+            return Err(ParseError::at(1, format!("{} pixels isn't a whole number of {}x{} layers",
+                                                 input.len(), WIDTH, HEIGHT)));
+        }
+        Ok(input.as_bytes()
+            .chunks(WIDTH * HEIGHT)
+            .map(|layer| layer.iter().map(|c| c - b'0').collect())
+            .collect())
+    }
+
+    fn part1(layers: &Self::Input) -> Result<Answer> {
+        Ok(part1(layers).into())
+    }
+
+    fn part2(layers: &Self::Input) -> Result<Answer> {
+        Ok(Answer::Image(part2(layers, WIDTH)))
+    }
+}
+
+fn part1(layers: &[Vec<u8>]) -> usize {
+    let min_zero_layer = layers
+        .iter()
+        .min_by_key(|&l| l.iter().filter(|&c| *c == 0).count())
+        .unwrap();
+    let num_ones = min_zero_layer
+        .iter()
+        .filter(|&x| *x == 1)
+        .count();
+    let num_twos = min_zero_layer
+        .iter()
+        .filter(|&x| *x == 2)
+        .count();
+
+    num_ones * num_twos
+}
+
+// Stacks the layers, the first on top, and returns the rows of lit pixels
+fn part2(layers: &[Vec<u8>], width: usize) -> Vec<Vec<bool>> {
+    let mut image = vec![false; layers.first().map_or(0, Vec::len)];
+    for layer in layers.iter().rev() {
+        for (pixel, colour) in image.iter_mut().zip(layer) {
+            match colour {
+                0 => *pixel = false,
+                1 => *pixel = true,
+                _ => ()
+            }
+        }
+    }
+    image.chunks(width).map(|row| row.to_vec()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Day08::parse("0123").unwrap_err().to_string(),
+                   "line 1: 4 pixels isn't a whole number of 25x6 layers");
+        let mut input = "1".repeat(WIDTH * HEIGHT);
+        input += "2";
+        assert_eq!(Day08::parse(&input).unwrap_err().to_string(),
+                   "line 1: 151 pixels isn't a whole number of 25x6 layers");
+        input.pop();
+        input.replace_range(7..8, "a");
+        assert_eq!(Day08::parse(&input).unwrap_err().to_string(), "line 1: 'a' isn't a pixel");
+        assert!(Day08::parse("").is_err());
+        let layers = Day08::parse(&format!("{}\n", "2".repeat(2 * WIDTH * HEIGHT))).unwrap();
+        assert_eq!(layers, vec![vec![2; WIDTH * HEIGHT]; 2]);
+    }
+
+    #[test]
+    fn test_part1() {
+        // The puzzle's 3x2 example, where the first layer has fewest zeros
+        let layers = vec![vec![1, 2, 3, 4, 5, 6], vec![7, 8, 9, 0, 1, 2]];
+        assert_eq!(part1(&layers), 1);
+    }
+
+    #[test]
+    fn test_part2() {
+        // The puzzle's 2x2 example
+        let layers = vec![vec![0, 2, 2, 2], vec![1, 1, 2, 2], vec![2, 2, 1, 2], vec![0, 0, 0, 0]];
+        assert_eq!(part2(&layers, 2), vec![vec![false, true], vec![true, false]]);
+    }
+
+    #[test]
+    fn test_input() {
+        let input = fs::read_to_string("data/input.txt").expect("Error reading file");
+        let layers = Day08::parse(&input).unwrap();
+        assert_eq!(part1(&layers), 1320);
+        assert_eq!(Answer::Image(part2(&layers, WIDTH)).to_string(), [
+            "***   **  *   **  * ***  ",
+            "*  * *  * *   ** *  *  * ",
+            "*  * *     * * **   *  * ",
+            "***  *      *  * *  ***  ",
+            "* *  *  *   *  * *  * *  ",
+            "*  *  **    *  *  * *  * ",
+        ].join("\n"));
+    }
+}
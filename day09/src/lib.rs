@@ -0,0 +1,124 @@
+use common::{Answer, ParseError, Result, Solution};
+use intcode::options::Options;
+use intcode::Status;
+use std::error;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    Intcode(intcode::Error),
+    // The program stopped to read more input than its mode
+    AwaitingInput,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Intcode(e) => write!(f, "{}", e),
+            Error::AwaitingInput => write!(f, "Program is waiting for input"),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl From<intcode::Error> for Error {
+    fn from(e: intcode::Error) -> Error {
+        Error::Intcode(e)
+    }
+}
+
+pub struct Day09;
+
+impl Solution for Day09 {
+    const DAY: u32 = 9;
+    type Input = Vec<i64>;
+
+    fn parse(input: &str) -> ::std::result::Result<Vec<i64>, ParseError> {
+        intcode::parse_instructions(input).map_err(|e| ParseError::new(format!("Bad program: {}", e)))
+    }
+
+    fn part1(instructions: &Vec<i64>) -> Result<Answer> {
+        let output = part1(instructions, &Options::default())?;
+        Ok((*output.last().ok_or("No BOOST keycode")?).into())
+    }
+
+    fn part2(instructions: &Vec<i64>) -> Result<Answer> {
+        let output = part2(instructions, &Options::default())?;
+        Ok((*output.last().ok_or("No coordinates")?).into())
+    }
+}
+
+// Runs the program to completion on the selected engine, returning its
+// output
+fn run(instructions: &[i64], input: &[i64], options: &Options) -> ::std::result::Result<Vec<i64>, Error> {
+    let mut cpu = options.machine(instructions);
+    input.iter().for_each(|v| cpu.push_input(*v));
+    let status = cpu.run();
+    options.collect(cpu.as_ref());
+    match status? {
+        Status::Halted => Ok(cpu.take_output()),
+        _ => Err(Error::AwaitingInput),
+    }
+}
+
+pub fn part1(instructions: &[i64], options: &Options) -> ::std::result::Result<Vec<i64>, Error> {
+    run(instructions, &[1], options)
+}
+
+pub fn part2(instructions: &[i64], options: &Options) -> ::std::result::Result<Vec<i64>, Error> {
+    run(instructions, &[2], options)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use intcode::machine::Engine;
+
+    #[test]
+    fn day09_1() {
+        let mut input = "109,1,204,-1,1001,100,1,100,1008,100,16,101,1006,101,0,99".to_string();
+        let instructions = Day09::parse(&input).unwrap();
+        input.push(',');
+        for engine in Engine::ALL.iter() {
+            let options = Options::new(*engine);
+            let output = run(&instructions, &[], &options).unwrap().iter().map(|i| format!("{},", i)).collect::<String>();
+            assert_eq!(output, input);
+        }
+    }
+
+    #[test]
+    fn day09_2() {
+        let instructions = vec![1102,34915192,34915192,7,4,7,99,0];
+        for engine in Engine::ALL.iter() {
+            let options = Options::new(*engine);
+            assert_eq!(part1(&instructions, &options).unwrap().pop().unwrap().to_string().len(), 16);
+        }
+    }
+
+    #[test]
+    fn day09_3() {
+        let instructions = Day09::parse("104,1125899906842624,99").unwrap();
+        for engine in Engine::ALL.iter() {
+            let options = Options::new(*engine);
+            assert_eq!(part1(&instructions, &options).unwrap().pop().unwrap(), 1125899906842624i64);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let instructions = Day09::parse("3,3,42").unwrap();
+        let error = part1(&instructions, &Options::default()).unwrap_err();
+        assert_eq!(error, Error::Intcode(intcode::Error::UnknownOpcode { opcode: 42, ip: 2 }));
+        assert_eq!(Day09::part2(&instructions).unwrap_err().to_string(), "Unrecognised opcode: 42, ip=2");
+        assert!(Day09::part1(&Day09::parse("99").unwrap()).is_err());
+        let instructions = Day09::parse("3,0,3,0,99").unwrap();
+        assert_eq!(part2(&instructions, &Options::default()), Err(Error::AwaitingInput));
+
+        // Runs that fail still count
+        let options = Options::from_args(&["--stats".to_string()]).unwrap();
+        assert!(part1(&Day09::parse("3,3,42").unwrap(), &options).is_err());
+        options.finish("part1");
+        assert!(options.report().starts_with("{\"part1\": {\"instructions\": {\"input\": 1}"));
+    }
+}
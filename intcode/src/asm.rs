@@ -0,0 +1,275 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+
+use crate::debug::DebugInfo;
+
+/// A small Intcode assembly language, one instruction per line:
+///
+/// ```text
+/// ; count down from the input
+///         in   count
+/// loop:   out  count
+///         add  count, #-1, count
+///         jt   count, #loop
+///         hlt
+/// count:  data 0
+/// ```
+///
+/// Operands are position mode by default, `#x` for immediate mode and
+/// `rb`, `rb+n` or `rb-n` for relative mode. Values are numbers, labels or
+/// `label+n`; `rb` can't start a label. `data` emits its operands as raw cells.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Assembly {
+    pub program: Vec<i64>,
+    pub debug: DebugInfo,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub source: String,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.source, self.line, self.message)
+    }
+}
+
+// (mnemonic, opcode, parameter count, index of the written parameter)
+pub(crate) const MNEMONICS: [(&str, i64, usize, Option<usize>); 10] = [
+    ("add", 1, 3, Some(2)),
+    ("mul", 2, 3, Some(2)),
+    ("in", 3, 1, Some(0)),
+    ("out", 4, 1, None),
+    ("jt", 5, 2, None),
+    ("jf", 6, 2, None),
+    ("lt", 7, 3, Some(2)),
+    ("eq", 8, 3, Some(2)),
+    ("arb", 9, 1, None),
+    ("hlt", 99, 0, None),
+];
+
+enum Statement<'a> {
+    Instruction(i64, Vec<&'a str>),
+    Data(Vec<&'a str>),
+}
+
+struct Line<'a> {
+    number: usize,
+    address: usize,
+    statement: Statement<'a>,
+}
+
+pub fn assemble_file(path: &str) -> Result<Assembly, String> {
+    let source = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    assemble(path, &source).map_err(|e| e.to_string())
+}
+
+/// Assembles `text`, naming it `source` in errors and debug info.
+pub fn assemble(source: &str, text: &str) -> Result<Assembly, AsmError> {
+    let error = |line: usize, message: String| AsmError {
+        source: source.to_string(),
+        line,
+        message,
+    };
+
+    // First pass: labels and addresses
+    let mut labels = BTreeMap::new();
+    let mut lines = Vec::new();
+    let mut address = 0;
+    for (i, text) in text.lines().enumerate() {
+        let number = i + 1;
+        let mut text = text.split(';').next().unwrap().trim();
+        while let Some(colon) = text.find(':') {
+            let label = text[..colon].trim();
+            if !is_identifier(label) {
+                return Err(error(number, format!("invalid label: {}", label)));
+            }
+            if labels.insert(label.to_string(), address).is_some() {
+                return Err(error(number, format!("duplicate label: {}", label)));
+            }
+            text = text[colon + 1..].trim();
+        }
+        if text.is_empty() {
+            continue;
+        }
+
+        let (mnemonic, rest) = match text.find(char::is_whitespace) {
+            Some(i) => (&text[..i], text[i..].trim()),
+            None => (text, ""),
+        };
+        let operands: Vec<&str> = if rest.is_empty() {
+            Vec::new()
+        } else {
+            rest.split(',').map(str::trim).collect()
+        };
+        let statement = if mnemonic == "data" {
+            Statement::Data(operands)
+        } else {
+            let &(_, opcode, count, target) = MNEMONICS
+                .iter()
+                .find(|m| m.0 == mnemonic)
+                .ok_or_else(|| error(number, format!("unknown mnemonic: {}", mnemonic)))?;
+            if operands.len() != count {
+                return Err(error(
+                    number,
+                    format!(
+                        "{} takes {} operands, got {}",
+                        mnemonic,
+                        count,
+                        operands.len()
+                    ),
+                ));
+            }
+            if let Some(target) = target {
+                if operands[target].starts_with('#') {
+                    return Err(error(
+                        number,
+                        format!("{} can't write to an immediate", mnemonic),
+                    ));
+                }
+            }
+            Statement::Instruction(opcode, operands)
+        };
+        let len = match &statement {
+            Statement::Instruction(_, operands) => operands.len() + 1,
+            Statement::Data(values) => values.len(),
+        };
+        lines.push(Line {
+            number,
+            address,
+            statement,
+        });
+        address += len;
+    }
+
+    // Second pass: emit cells
+    let mut program = Vec::with_capacity(address);
+    let mut debug = DebugInfo {
+        source: source.to_string(),
+        labels,
+        lines: BTreeMap::new(),
+    };
+    for line in &lines {
+        debug.lines.insert(line.address, line.number);
+        let value = |s: &str| {
+            value(s, &debug.labels).ok_or_else(|| error(line.number, format!("bad value: {}", s)))
+        };
+        match &line.statement {
+            Statement::Instruction(opcode, operands) => {
+                let mut instruction = *opcode;
+                let mut params = Vec::new();
+                for (i, operand) in operands.iter().enumerate() {
+                    let (mode, param) = if let Some(v) = operand.strip_prefix('#') {
+                        (1, value(v)?)
+                    } else if let Some(rest) = operand.strip_prefix("rb") {
+                        let offset = offset(rest).ok_or_else(|| {
+                            error(line.number, format!("bad relative operand: {}", operand))
+                        })?;
+                        (2, offset)
+                    } else {
+                        (0, value(operand)?)
+                    };
+                    instruction += mode * 10i64.pow(i as u32 + 2);
+                    params.push(param);
+                }
+                program.push(instruction);
+                program.extend(params);
+            }
+            Statement::Data(values) => {
+                for v in values {
+                    program.push(value(v)?);
+                }
+            }
+        }
+    }
+    Ok(Assembly { program, debug })
+}
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+// A number, a label or `label+n`/`label-n`
+pub(crate) fn value(s: &str, labels: &BTreeMap<String, usize>) -> Option<i64> {
+    let s = s.trim();
+    if let Ok(n) = s.parse::<i64>() {
+        return Some(n);
+    }
+    let split = s.find(['+', '-']).unwrap_or(s.len());
+    let base = *labels.get(s[..split].trim())? as i64;
+    Some(base + offset(&s[split..])?)
+}
+
+// Empty, `+n` or `-n`
+fn offset(s: &str) -> Option<i64> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Some(0);
+    }
+    let (sign, n) = match s.split_at(1) {
+        ("+", n) => (1, n),
+        ("-", n) => (-1, n),
+        _ => return None,
+    };
+    n.trim().parse::<i64>().ok().map(|n| sign * n)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Computer;
+
+    const COUNTDOWN: &str = "\
+; count down from the input
+        in   count
+loop:   out  count
+        add  count, #-1, count
+        jt   count, #loop
+        hlt
+count:  data 0
+";
+
+    #[test]
+    fn test_assemble() {
+        let asm = assemble("countdown.asm", COUNTDOWN).unwrap();
+        assert_eq!(
+            asm.program,
+            vec![3, 12, 4, 12, 1001, 12, -1, 12, 1005, 12, 2, 99, 0]
+        );
+        let mut cpu = Computer::new(asm.program);
+        cpu.push_input(3);
+        cpu.run();
+        assert_eq!(cpu.output, vec![3, 2, 1]);
+        assert_eq!(asm.debug.labels["loop"], 2);
+        assert_eq!(asm.debug.line(4), Some(4));
+    }
+
+    #[test]
+    fn test_relative() {
+        let asm = assemble("t", "arb #10\nadd #7, #0, rb+2\nout rb+2\nout rb-10\nhlt").unwrap();
+        assert_eq!(asm.program[..6], [109, 10, 21101, 7, 0, 2]);
+        let mut cpu = Computer::new(asm.program);
+        cpu.run();
+        assert_eq!(cpu.output, vec![7, 109]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = |text| assemble("bad.asm", text).unwrap_err().to_string();
+        assert_eq!(err("hlt\nfoo 1"), "bad.asm:2: unknown mnemonic: foo");
+        assert_eq!(err("add 1, 2"), "bad.asm:1: add takes 3 operands, got 2");
+        assert_eq!(err("in #1"), "bad.asm:1: in can't write to an immediate");
+        assert_eq!(err("out nowhere"), "bad.asm:1: bad value: nowhere");
+        assert_eq!(err("a: a: hlt"), "bad.asm:1: duplicate label: a");
+    }
+}
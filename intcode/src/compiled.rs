@@ -0,0 +1,119 @@
+use std::time::Instant;
+
+use crate::machine::{Core, Machine, Op};
+use crate::stats::Stats;
+use crate::{Error, Status};
+
+/// An engine that translates each instruction once, with its parameters
+/// resolved to operands, and replays the translation on later visits. A
+/// write drops every cached instruction that could include the cell.
+#[derive(Clone)]
+pub struct Compiled {
+    core: Core,
+    cache: Vec<Option<Op>>,
+}
+
+// The longest instruction has three parameters
+const MAX_LEN: usize = 4;
+
+impl Compiled {
+    pub fn new(program: &[i64]) -> Compiled {
+        Compiled {
+            core: Core::new(program),
+            cache: vec![None; program.len()],
+        }
+    }
+
+    fn step(&mut self) -> Result<Option<Status>, Error> {
+        let ip = self.core.ip;
+        let op = match self.cache.get(ip as usize).cloned().flatten() {
+            Some(op) => op,
+            _ => {
+                let (opcode, modes) = self.core.opcode(ip)?;
+                let op = self.core.op(ip, opcode, modes)?;
+                if let Some(entry) = self.cache.get_mut(ip as usize) {
+                    *entry = Some(op);
+                }
+                op
+            }
+        };
+        let status = self.core.execute(op)?;
+        if let Some(address) = self.core.written.take() {
+            self.invalidate(address);
+        }
+        Ok(status)
+    }
+
+    fn invalidate(&mut self, address: usize) {
+        let start = address.saturating_sub(MAX_LEN - 1);
+        let end = (address + 1).min(self.cache.len());
+        for entry in self.cache.iter_mut().take(end).skip(start) {
+            *entry = None;
+        }
+    }
+}
+
+impl Machine for Compiled {
+    fn load(&mut self, program: &[i64]) {
+        let stats = self.core.stats.as_ref().map(|_| Stats::default());
+        *self = Compiled::new(program);
+        self.core.stats = stats;
+    }
+
+    fn reset(&mut self) {
+        self.core.reset();
+        self.cache.iter_mut().for_each(|entry| *entry = None);
+    }
+
+    fn push_input(&mut self, value: i64) {
+        self.core.input.push_back(value);
+    }
+
+    fn run(&mut self) -> Result<Status, Error> {
+        if self.core.halted {
+            return Ok(Status::Halted);
+        }
+        let start = Instant::now();
+        let result = loop {
+            match self.step() {
+                Ok(Some(status)) => break Ok(status),
+                Ok(None) => (),
+                Err(e) => break Err(e),
+            }
+        };
+        if let Some(stats) = self.core.stats.as_mut() {
+            stats.elapsed += start.elapsed();
+        }
+        result
+    }
+
+    fn take_output(&mut self) -> Vec<i64> {
+        self.core.output.drain(..).collect()
+    }
+
+    fn status(&self) -> Status {
+        self.core.status()
+    }
+
+    fn read(&self, address: usize) -> i64 {
+        self.core.read(address)
+    }
+
+    fn write(&mut self, address: usize, value: i64) {
+        self.core.write(address, value);
+        self.core.written = None;
+        self.invalidate(address);
+    }
+
+    fn fork(&self) -> Box<dyn Machine + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn collect_stats(&mut self) {
+        self.core.stats = Some(Stats::default());
+    }
+
+    fn stats(&self) -> Option<&Stats> {
+        self.core.stats.as_ref()
+    }
+}
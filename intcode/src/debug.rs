@@ -0,0 +1,162 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::str::FromStr;
+
+use crate::asm;
+
+/// Maps an assembled program's addresses back to its source. Saved next to
+/// the program as `<program>.dbg`, one record per line:
+///
+/// ```text
+/// source countdown.asm
+/// label loop 2
+/// line 2 3
+/// ```
+///
+/// where `line 2 3` says the instruction at address 2 came from line 3.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DebugInfo {
+    pub source: String,
+    pub labels: BTreeMap<String, usize>,
+    pub lines: BTreeMap<usize, usize>,
+}
+
+impl DebugInfo {
+    pub fn sidecar(program: &str) -> String {
+        format!("{}.dbg", program)
+    }
+
+    pub fn load(path: &str) -> Result<DebugInfo, String> {
+        let contents = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        contents.parse()
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    /// The source line of the instruction or data covering `address`.
+    pub fn line(&self, address: usize) -> Option<usize> {
+        self.lines.range(..=address).next_back().map(|(_, l)| *l)
+    }
+
+    /// `address` relative to the closest label at or before it, e.g.
+    /// `loop_start+3`.
+    pub fn symbol(&self, address: usize) -> Option<String> {
+        self.labels
+            .iter()
+            .filter(|(_, a)| **a <= address)
+            .max_by_key(|(_, a)| **a)
+            .map(|(name, a)| match address - a {
+                0 => name.clone(),
+                offset => format!("{}+{}", name, offset),
+            })
+    }
+
+    /// Describes `address` for people, e.g. `loop_start+3 (prog.asm:14)`.
+    pub fn location(&self, address: usize) -> String {
+        let symbol = self.symbol(address).unwrap_or_else(|| address.to_string());
+        match self.line(address) {
+            Some(line) => format!("{} ({}:{})", symbol, self.source, line),
+            None => symbol,
+        }
+    }
+
+    /// Parses an address given as a number, label or `label+n`.
+    pub fn resolve(&self, s: &str) -> Option<usize> {
+        asm::value(s, &self.labels)
+            .filter(|a| *a >= 0)
+            .map(|a| a as usize)
+    }
+}
+
+impl fmt::Display for DebugInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "source {}", self.source)?;
+        for (name, address) in &self.labels {
+            writeln!(f, "label {} {}", name, address)?;
+        }
+        for (address, line) in &self.lines {
+            writeln!(f, "line {} {}", address, line)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for DebugInfo {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<DebugInfo, String> {
+        let mut debug = DebugInfo::default();
+        let number = |s: &str| s.parse::<usize>().map_err(|e| format!("{}: {}", s, e));
+        for record in s.lines().map(str::trim) {
+            if record.is_empty() || record.starts_with('#') {
+                continue;
+            }
+            let words: Vec<&str> = record.split_whitespace().collect();
+            match words[..] {
+                ["source", ref source @ ..] => debug.source = source.join(" "),
+                ["label", name, address] => {
+                    debug.labels.insert(name.to_string(), number(address)?);
+                }
+                ["line", address, line] => {
+                    debug.lines.insert(number(address)?, number(line)?);
+                }
+                _ => return Err(format!("Malformed debug record: {}", record)),
+            }
+        }
+        Ok(debug)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+    use crate::machine::Machine;
+    use crate::Computer;
+
+    const SOURCE: &str = "\
+start:  in   n
+loop_start:
+        out  n
+        add  n, #-1, n
+        jt   n, #loop_start
+        hlt
+n:      data 0
+";
+
+    #[test]
+    fn test_location() {
+        let debug = assemble("prog.asm", SOURCE).unwrap().debug;
+        assert_eq!(debug.location(0), "start (prog.asm:1)");
+        assert_eq!(debug.location(5), "loop_start+3 (prog.asm:4)");
+        assert_eq!(debug.resolve("loop_start+2"), Some(4));
+        assert_eq!(debug.resolve("7"), Some(7));
+        assert_eq!(debug.resolve("nowhere"), None);
+        assert_eq!(DebugInfo::default().location(3), "3");
+    }
+
+    #[test]
+    fn test_explain() {
+        let asm = assemble("bad.asm", "out #1\nbroken: data 42").unwrap();
+        let mut cpu = Computer::new(asm.program);
+        cpu.debug = Some(asm.debug);
+        let error = Machine::run(&mut cpu).unwrap_err();
+        assert_eq!(
+            cpu.explain(&error),
+            "Unrecognised opcode: 42, ip=2 at broken (bad.asm:2)"
+        );
+    }
+
+    #[test]
+    fn test_sidecar() {
+        let debug = assemble("prog.asm", SOURCE).unwrap().debug;
+        let text = debug.to_string();
+        assert!(text.starts_with("source prog.asm\nlabel loop_start 2\n"));
+        assert_eq!(text.parse(), Ok(debug));
+        assert!("line 1".parse::<DebugInfo>().is_err());
+    }
+}
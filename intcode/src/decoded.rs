@@ -0,0 +1,113 @@
+use std::time::Instant;
+
+use crate::machine::{Core, Machine};
+use crate::stats::Stats;
+use crate::{Error, Status};
+
+/// An engine that decodes each instruction word once. Opcodes and modes are
+/// cached by address and dropped when the word is overwritten; operands are
+/// still read from memory every time.
+#[derive(Clone)]
+pub struct Decoded {
+    core: Core,
+    cache: Vec<Option<(i32, [u8; 3])>>,
+}
+
+impl Decoded {
+    pub fn new(program: &[i64]) -> Decoded {
+        Decoded {
+            core: Core::new(program),
+            cache: vec![None; program.len()],
+        }
+    }
+
+    fn step(&mut self) -> Result<Option<Status>, Error> {
+        let ip = self.core.ip;
+        let cached = self.cache.get(ip as usize).cloned().flatten();
+        let (opcode, modes) = match cached {
+            Some(decoded) => decoded,
+            _ => {
+                let decoded = self.core.opcode(ip)?;
+                if let Some(entry) = self.cache.get_mut(ip as usize) {
+                    *entry = Some(decoded);
+                }
+                decoded
+            }
+        };
+        let op = self.core.op(ip, opcode, modes)?;
+        let status = self.core.execute(op)?;
+        if let Some(address) = self.core.written.take() {
+            if let Some(entry) = self.cache.get_mut(address) {
+                *entry = None;
+            }
+        }
+        Ok(status)
+    }
+}
+
+impl Machine for Decoded {
+    fn load(&mut self, program: &[i64]) {
+        let stats = self.core.stats.as_ref().map(|_| Stats::default());
+        *self = Decoded::new(program);
+        self.core.stats = stats;
+    }
+
+    fn reset(&mut self) {
+        self.core.reset();
+        self.cache.iter_mut().for_each(|entry| *entry = None);
+    }
+
+    fn push_input(&mut self, value: i64) {
+        self.core.input.push_back(value);
+    }
+
+    fn run(&mut self) -> Result<Status, Error> {
+        if self.core.halted {
+            return Ok(Status::Halted);
+        }
+        let start = Instant::now();
+        let result = loop {
+            match self.step() {
+                Ok(Some(status)) => break Ok(status),
+                Ok(None) => (),
+                Err(e) => break Err(e),
+            }
+        };
+        if let Some(stats) = self.core.stats.as_mut() {
+            stats.elapsed += start.elapsed();
+        }
+        result
+    }
+
+    fn take_output(&mut self) -> Vec<i64> {
+        self.core.output.drain(..).collect()
+    }
+
+    fn status(&self) -> Status {
+        self.core.status()
+    }
+
+    fn read(&self, address: usize) -> i64 {
+        self.core.read(address)
+    }
+
+    fn write(&mut self, address: usize, value: i64) {
+        self.core.write(address, value);
+        if let Some(entry) = self.cache.get_mut(address) {
+            *entry = None;
+        }
+        self.core.written = None;
+    }
+
+    fn fork(&self) -> Box<dyn Machine + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn collect_stats(&mut self) {
+        self.core.stats = Some(Stats::default());
+    }
+
+    fn stats(&self) -> Option<&Stats> {
+        self.core.stats.as_ref()
+    }
+}
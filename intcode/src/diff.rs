@@ -0,0 +1,196 @@
+use std::fmt;
+use std::ops::Range;
+
+use crate::disasm::{disassemble, Item};
+
+/// One difference between two programs, with addresses in each.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// The same instruction with a different parameter (or data cell).
+    Constant {
+        left: usize,
+        right: usize,
+        instruction: String,
+        from: String,
+        to: String,
+    },
+    /// A different instruction in the same place.
+    Instruction {
+        left: usize,
+        right: usize,
+        from: String,
+        to: String,
+    },
+    /// Instructions only in the left program.
+    Deleted {
+        left: Range<usize>,
+        items: Vec<String>,
+    },
+    /// Instructions only in the right program.
+    Inserted {
+        right: Range<usize>,
+        items: Vec<String>,
+    },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Change::Constant {
+                left,
+                right,
+                instruction,
+                from,
+                to,
+            } => write!(
+                f,
+                "constant    {}/{}  {}: {} -> {}",
+                left, right, instruction, from, to
+            ),
+            Change::Instruction {
+                left,
+                right,
+                from,
+                to,
+            } => write!(f, "instruction {}/{}  {} -> {}", left, right, from, to),
+            Change::Deleted { left, items } => write!(
+                f,
+                "deleted     {}..{}  {}",
+                left.start,
+                left.end,
+                items.join("; ")
+            ),
+            Change::Inserted { right, items } => write!(
+                f,
+                "inserted    {}..{}  {}",
+                right.start,
+                right.end,
+                items.join("; ")
+            ),
+        }
+    }
+}
+
+/// Compares two programs by their disassembled structure rather than cell by
+/// cell, so an inserted instruction doesn't make everything after it look
+/// different.
+///
+/// Items are aligned on their shape (opcode and modes, or data) with a
+/// longest common subsequence. Aligned items that differ are reported
+/// parameter by parameter as `Constant` changes. Between aligned stretches,
+/// runs of equal length pair up as `Instruction` changes and anything else
+/// is `Deleted` from the left and `Inserted` in the right.
+pub fn diff(left: &[i64], right: &[i64]) -> Vec<Change> {
+    let a = disassemble(left);
+    let b = disassemble(right);
+
+    // lcs[i][j] is the length of the longest alignment of a[i..] and b[j..]
+    let mut lcs = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i].word() == b[j].word() {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    let (mut gap_a, mut gap_b) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i].word() == b[j].word() {
+            unmatched(&a[gap_a..i], &b[gap_b..j], &mut changes);
+            constants(&a[i], &b[j], &mut changes);
+            i += 1;
+            j += 1;
+            gap_a = i;
+            gap_b = j;
+        } else if j == b.len() || (i < a.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    unmatched(&a[gap_a..], &b[gap_b..], &mut changes);
+    changes
+}
+
+fn constants(a: &Item, b: &Item, changes: &mut Vec<Change>) {
+    let (pa, pb) = (a.params(), b.params());
+    for k in 0..pa.len() {
+        if pa[k] != pb[k] {
+            changes.push(Change::Constant {
+                left: a.address(),
+                right: b.address(),
+                instruction: a.to_string(),
+                from: a.operand(k),
+                to: b.operand(k),
+            });
+        }
+    }
+}
+
+fn unmatched(a: &[Item], b: &[Item], changes: &mut Vec<Change>) {
+    if a.len() == b.len() {
+        for (x, y) in a.iter().zip(b) {
+            changes.push(Change::Instruction {
+                left: x.address(),
+                right: y.address(),
+                from: x.to_string(),
+                to: y.to_string(),
+            });
+        }
+        return;
+    }
+    if let (Some(first), Some(last)) = (a.first(), a.last()) {
+        changes.push(Change::Deleted {
+            left: first.address()..last.address() + last.size(),
+            items: a.iter().map(Item::to_string).collect(),
+        });
+    }
+    if let (Some(first), Some(last)) = (b.first(), b.last()) {
+        changes.push(Change::Inserted {
+            right: first.address()..last.address() + last.size(),
+            items: b.iter().map(Item::to_string).collect(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+
+    fn program(source: &str) -> Vec<i64> {
+        assemble("t", source).unwrap().program
+    }
+
+    #[test]
+    fn test_diff() {
+        let left = program("in 30\nadd 30, #5, 30\nout 30\nmul 30, #2, 31\nhlt\ndata 7");
+        let right = program(
+            "in 30\nadd 30, #6, 30\nlt 30, #9, 32\narb #1\nout 30\nadd 30, #2, 31\nhlt\ndata 8",
+        );
+        let changes: Vec<String> = diff(&left, &right).iter().map(Change::to_string).collect();
+        assert_eq!(
+            changes,
+            vec![
+                "constant    2/2  add 30, #5, 30: #5 -> #6",
+                "inserted    6..12  lt 30, #9, 32; arb #1",
+                "instruction 8/14  mul 30, #2, 31 -> add 30, #2, 31",
+                "constant    13/19  data 7: 7 -> 8",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_same() {
+        let p = program("in 10\nout 10\nhlt");
+        assert!(diff(&p, &p).is_empty());
+        let changes = diff(&p, &p[..4]);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].to_string(), "deleted     4..5  hlt");
+    }
+}
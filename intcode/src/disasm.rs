@@ -0,0 +1,173 @@
+use std::fmt;
+
+use crate::asm::MNEMONICS;
+use crate::Computer;
+
+/// One decoded piece of a program: an instruction, or a cell that doesn't
+/// decode as one. Displays in the assembler's syntax.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Item {
+    Instruction {
+        address: usize,
+        opcode: i32,
+        modes: [u8; 3],
+        params: Vec<i64>,
+    },
+    Data {
+        address: usize,
+        value: i64,
+    },
+}
+
+impl Item {
+    pub fn address(&self) -> usize {
+        match self {
+            Item::Instruction { address, .. } | Item::Data { address, .. } => *address,
+        }
+    }
+
+    /// The number of cells the item takes up.
+    pub fn size(&self) -> usize {
+        match self {
+            Item::Instruction { params, .. } => params.len() + 1,
+            Item::Data { .. } => 1,
+        }
+    }
+
+    /// The instruction cell, or `None` for data. Items with the same word
+    /// have the same shape and differ only in their parameters.
+    pub fn word(&self) -> Option<i64> {
+        match self {
+            Item::Instruction { opcode, modes, .. } => Some(
+                *opcode as i64
+                    + modes
+                        .iter()
+                        .enumerate()
+                        .map(|(i, m)| *m as i64 * 10i64.pow(i as u32 + 2))
+                        .sum::<i64>(),
+            ),
+            Item::Data { .. } => None,
+        }
+    }
+
+    /// The parameters, or the value of a data cell.
+    pub fn params(&self) -> Vec<i64> {
+        match self {
+            Item::Instruction { params, .. } => params.clone(),
+            Item::Data { value, .. } => vec![*value],
+        }
+    }
+
+    /// Parameter `i` as written in assembly, e.g. `#5`, `20` or `rb-2`.
+    pub fn operand(&self, i: usize) -> String {
+        match self {
+            Item::Instruction { modes, params, .. } => match modes[i] {
+                1 => format!("#{}", params[i]),
+                2 if params[i] < 0 => format!("rb{}", params[i]),
+                2 => format!("rb+{}", params[i]),
+                _ => params[i].to_string(),
+            },
+            Item::Data { value, .. } => value.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (mnemonic, count) = match self {
+            Item::Instruction { opcode, params, .. } => (mnemonic(*opcode), params.len()),
+            Item::Data { .. } => ("data", 1),
+        };
+        let operands: Vec<String> = (0..count).map(|i| self.operand(i)).collect();
+        if operands.is_empty() {
+            write!(f, "{}", mnemonic)
+        } else {
+            write!(f, "{} {}", mnemonic, operands.join(", "))
+        }
+    }
+}
+
+fn mnemonic(opcode: i32) -> &'static str {
+    MNEMONICS
+        .iter()
+        .find(|m| m.1 == opcode as i64)
+        .map_or("?", |m| m.0)
+}
+
+/// Decodes a program from the start, cell by cell. Anything that isn't a
+/// valid instruction (unknown opcode, bad mode, a write in immediate mode or
+/// running off the end) becomes a one-cell `Item::Data`, and decoding
+/// carries on from the next cell.
+pub fn disassemble(program: &[i64]) -> Vec<Item> {
+    disassemble_from(program, 0, usize::MAX)
+}
+
+/// As `disassemble`, but decodes at most `count` items from `address`.
+pub fn disassemble_from(program: &[i64], mut address: usize, count: usize) -> Vec<Item> {
+    let mut items = Vec::new();
+    while address < program.len() && items.len() < count {
+        let item = instruction(program, address).unwrap_or(Item::Data {
+            address,
+            value: program[address],
+        });
+        address += item.size();
+        items.push(item);
+    }
+    items
+}
+
+fn instruction(program: &[i64], address: usize) -> Option<Item> {
+    let cell = program[address];
+    if cell <= 0 || cell > 99999 {
+        return None;
+    }
+    let (opcode, modes) = Computer::decode(cell as i32);
+    let &(_, _, count, target) = MNEMONICS.iter().find(|m| m.1 == opcode as i64)?;
+    let valid = modes.iter().enumerate().all(|(i, &mode)| {
+        if i >= count {
+            mode == 0
+        } else {
+            mode <= 2 && !(mode == 1 && target == Some(i))
+        }
+    });
+    if !valid || address + count >= program.len() {
+        return None;
+    }
+    Some(Item::Instruction {
+        address,
+        opcode,
+        modes,
+        params: program[address + 1..=address + count].to_vec(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+
+    #[test]
+    fn test_disassemble() {
+        let source = "in 20\nadd rb-2, #-1, rb+3\njt 20, #0\nhlt";
+        let asm = assemble("t", source).unwrap();
+        let mut program = asm.program;
+        program.extend(vec![11101, 4]);
+        let items = disassemble(&program);
+        let text: Vec<String> = items.iter().map(Item::to_string).collect();
+        assert_eq!(
+            text,
+            vec![
+                "in 20",
+                "add rb-2, #-1, rb+3",
+                "jt 20, #0",
+                "hlt",
+                "data 11101",
+                "data 4"
+            ]
+        );
+        assert_eq!(items[2].address(), 6);
+        assert_eq!(items[2].word(), Some(1005));
+        assert_eq!(items[4].word(), None);
+        assert_eq!(disassemble_from(&program, 6, 2), items[2..4]);
+    }
+}
@@ -0,0 +1,729 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fmt::Write;
+use std::ops::Range;
+
+use crate::asm::MNEMONICS;
+use crate::disasm::{disassemble_from, Item};
+
+/// Memory past the end of the program, as `Computer::new` gives it.
+const PADDING: usize = 10000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExportError {
+    /// An instruction writes into the program's own code.
+    SelfModifying { ip: usize, address: usize },
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExportError::SelfModifying { ip, address } => write!(
+                f,
+                "Self-modifying code: instruction at {} writes to code at {}",
+                ip, address
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Source {
+    Position(i64),
+    Stack,
+}
+
+// Instructions found by following control from some starting points
+#[derive(Debug, Clone, Default)]
+struct Graph {
+    instructions: BTreeMap<usize, Item>,
+    // Reached cells that aren't valid instructions
+    traps: BTreeSet<usize>,
+    leaders: BTreeSet<usize>,
+    // Where the dynamic jumps read their targets from
+    sources: BTreeSet<Source>,
+}
+
+impl Graph {
+    fn explore(&mut self, program: &[i64], start: usize) {
+        self.leaders.insert(start);
+        let mut work = vec![start];
+        while let Some(address) = work.pop() {
+            if self.instructions.contains_key(&address) || self.traps.contains(&address) {
+                continue;
+            }
+            let item = match decode(program, address) {
+                Some(item) => item,
+                None => {
+                    self.traps.insert(address);
+                    self.leaders.insert(address);
+                    continue;
+                }
+            };
+            if let Some(source) = jump_source(&item) {
+                self.sources.insert(source);
+            }
+            let (jump, fall) = successors(&item);
+            if let Some(target) = jump {
+                self.leaders.insert(target);
+                work.push(target);
+            }
+            if let Some(next) = fall {
+                if jump.is_some() || is_jump(&item) {
+                    self.leaders.insert(next);
+                }
+                work.push(next);
+            }
+            self.instructions.insert(address, item);
+        }
+    }
+
+    // A position-mode write into code. Traps count as code: writing to one
+    // is how a program patches in an instruction it hasn't got yet.
+    fn self_modification(&self) -> Option<ExportError> {
+        let mut cells = self.traps.clone();
+        for item in self.instructions.values() {
+            cells.extend(item.address()..item.address() + item.size());
+        }
+        self.instructions.values().find_map(|item| {
+            let address = static_write(item)?;
+            if cells.contains(&address) {
+                Some(ExportError::SelfModifying {
+                    ip: item.address(),
+                    address,
+                })
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// A straight run of instructions entered only at `start`. A block with no
+/// instructions is a trap: control reaches a cell that isn't a valid
+/// instruction, which the interpreter would report at run time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Block {
+    pub start: usize,
+    pub items: Vec<Item>,
+    /// Where control goes if it falls off the end of the block.
+    pub next: Option<usize>,
+}
+
+/// The control flow graph of a program, found by decoding from address 0
+/// and following jumps, for translating programs that don't modify their
+/// own code into other languages.
+///
+/// Jumps to addresses read from memory (function returns, usually) can go
+/// anywhere, so constants written by `add`/`mul` with two immediate
+/// operands to where those jumps read from (a fixed address, or anywhere
+/// for jumps through the stack), which is how return addresses get there,
+/// are taken as possible targets too if decoding from them looks like code. The translations fail at run time on a jump to
+/// anywhere else, and on a write through a relative operand into code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cfg {
+    pub program: Vec<i64>,
+    pub blocks: Vec<Block>,
+    /// Cells holding instructions, as sorted ranges.
+    pub code: Vec<Range<usize>>,
+}
+
+impl Cfg {
+    /// Builds the graph, failing if an instruction writes into code through
+    /// a position-mode operand, or into a cell control reaches that isn't
+    /// an instruction yet.
+    pub fn build(program: &[i64]) -> Result<Cfg, ExportError> {
+        let mut graph = Graph::default();
+        graph.explore(program, 0);
+        if let Some(error) = graph.self_modification() {
+            return Err(error);
+        }
+
+        // Possible return addresses only count if decoding from them finds
+        // plausible code: no bad opcodes and no writes into code
+        let mut rejected = BTreeSet::new();
+        loop {
+            let candidates: Vec<usize> = graph
+                .instructions
+                .values()
+                .filter_map(stored_constant)
+                .filter(|(target, destination)| {
+                    !graph.leaders.contains(target)
+                        && !rejected.contains(target)
+                        && (graph.sources.contains(destination)
+                            || graph.sources.contains(&Source::Stack))
+                })
+                .map(|(target, _)| target)
+                .collect();
+            if candidates.is_empty() {
+                break;
+            }
+            for target in candidates {
+                let mut trial = graph.clone();
+                trial.explore(program, target);
+                if trial.traps.len() == graph.traps.len() && trial.self_modification().is_none() {
+                    graph = trial;
+                } else {
+                    rejected.insert(target);
+                }
+            }
+        }
+
+        let Graph {
+            instructions,
+            traps,
+            leaders,
+            ..
+        } = graph;
+        let mut cells = traps;
+        for item in instructions.values() {
+            cells.extend(item.address()..item.address() + item.size());
+        }
+        let mut code: Vec<Range<usize>> = Vec::new();
+        for cell in cells {
+            match code.last_mut() {
+                Some(range) if range.end == cell => range.end += 1,
+                _ => code.push(cell..cell + 1),
+            }
+        }
+
+        let blocks = leaders
+            .iter()
+            .map(|&start| {
+                let mut items = Vec::new();
+                let mut address = start;
+                while let Some(item) = instructions.get(&address) {
+                    items.push(item.clone());
+                    address += item.size();
+                    let (_, fall) = successors(item);
+                    if fall.is_none() {
+                        return Block {
+                            start,
+                            items,
+                            next: None,
+                        };
+                    }
+                    if is_jump(item) || leaders.contains(&address) {
+                        break;
+                    }
+                }
+                let next = if items.is_empty() {
+                    None
+                } else {
+                    Some(address)
+                };
+                Block { start, items, next }
+            })
+            .collect();
+        Ok(Cfg {
+            program: program.to_vec(),
+            blocks,
+            code,
+        })
+    }
+
+    /// A standalone Rust program that reads input numbers from stdin and
+    /// prints each output on its own line.
+    pub fn to_rust(&self, name: &str) -> String {
+        let mut out = String::new();
+        let w = &mut out;
+        writeln!(
+            w,
+            "// {} translated from Intcode by `intcode export`.",
+            name
+        )
+        .unwrap();
+        writeln!(
+            w,
+            "// Reads input numbers from stdin and prints each output on its own line."
+        )
+        .unwrap();
+        writeln!(w, "#![allow(unused, unreachable_code)]").unwrap();
+        writeln!(w, "use std::io::{{self, Read}};\n").unwrap();
+        writeln!(
+            w,
+            "const PROGRAM: [i64; {}] = {:?};\n",
+            self.program.len(),
+            self.program
+        )
+        .unwrap();
+        let code: Vec<(usize, usize)> = self.code.iter().map(|r| (r.start, r.end)).collect();
+        writeln!(
+            w,
+            "const CODE: [(i64, i64); {}] = {:?};\n",
+            code.len(),
+            code
+        )
+        .unwrap();
+        w.push_str(RUST_PRELUDE);
+        writeln!(w, "    m.resize(PROGRAM.len() + {}, 0);", PADDING).unwrap();
+        w.push_str("    let mut ip: i64 = 0;\n    let mut base: i64 = 0;\n");
+        w.push_str("    loop {\n        match ip {\n");
+        for block in &self.blocks {
+            writeln!(w, "            {} => {{", block.start).unwrap();
+            if block.items.is_empty() {
+                writeln!(
+                    w,
+                    "                panic!(\"{}\");",
+                    trap(&self.program, block.start)
+                )
+                .unwrap();
+            }
+            for item in &block.items {
+                writeln!(w, "                // {}: {}", item.address(), item).unwrap();
+                writeln!(w, "                {}", rust(item)).unwrap();
+            }
+            match block.next {
+                Some(next) => writeln!(w, "                ip = {};", next).unwrap(),
+                None if block.items.is_empty() => (),
+                None => writeln!(w, "                unreachable!();").unwrap(),
+            }
+            writeln!(w, "            }}").unwrap();
+        }
+        w.push_str(
+            "            _ => panic!(\"Jump to {}, which isn't a known instruction\", ip),\n",
+        );
+        w.push_str("        }\n    }\n}\n");
+        out
+    }
+
+    /// A WebAssembly text module exporting `run` and its memory, with
+    /// input and output imported as `env.input` and `env.output`. Memory
+    /// holds one i64 per Intcode cell.
+    pub fn to_wat(&self, name: &str) -> String {
+        let mut out = String::new();
+        let w = &mut out;
+        let cells = self.program.len() + PADDING;
+        writeln!(w, ";; {} translated from Intcode by `intcode export`", name).unwrap();
+        w.push_str("(module\n");
+        w.push_str("  (import \"env\" \"input\" (func $input (result i64)))\n");
+        w.push_str("  (import \"env\" \"output\" (func $output (param i64)))\n");
+        writeln!(
+            w,
+            "  (memory (export \"memory\") {})",
+            (cells * 8).div_ceil(65536)
+        )
+        .unwrap();
+        w.push_str("  (data (i32.const 0) \"");
+        for value in &self.program {
+            for byte in value.to_le_bytes().iter() {
+                write!(w, "\\{:02x}", byte).unwrap();
+            }
+        }
+        w.push_str("\")\n");
+        writeln!(w, "  (func $get (param $a i64) (result i64)").unwrap();
+        writeln!(
+            w,
+            "    (if (i64.ge_u (local.get $a) (i64.const {})) (then unreachable))",
+            cells
+        )
+        .unwrap();
+        w.push_str("    (i64.load (i32.wrap_i64 (i64.shl (local.get $a) (i64.const 3)))))\n");
+        w.push_str("  (func $set (param $a i64) (param $v i64)\n");
+        writeln!(
+            w,
+            "    (if (i64.ge_u (local.get $a) (i64.const {})) (then unreachable))",
+            cells
+        )
+        .unwrap();
+        for range in &self.code {
+            writeln!(
+                w,
+                "    (if (i32.and (i64.ge_s (local.get $a) (i64.const {})) (i64.lt_s (local.get $a) (i64.const {}))) (then unreachable))",
+                range.start, range.end
+            )
+            .unwrap();
+        }
+        w.push_str("    (i64.store (i32.wrap_i64 (i64.shl (local.get $a) (i64.const 3))) (local.get $v)))\n");
+        w.push_str("  (func (export \"run\")\n    (local $ip i64) (local $base i64)\n");
+        w.push_str("    (loop $dispatch\n      (block $bad\n");
+        for block in self.blocks.iter().rev() {
+            writeln!(w, "      (block $b{}", block.start).unwrap();
+        }
+        let last = self.blocks.last().map_or(0, |b| b.start);
+        writeln!(
+            w,
+            "        (if (i64.gt_u (local.get $ip) (i64.const {})) (then (br $bad)))",
+            last
+        )
+        .unwrap();
+        let starts: BTreeSet<usize> = self.blocks.iter().map(|b| b.start).collect();
+        let labels: Vec<String> = (0..=last)
+            .map(|a| {
+                if starts.contains(&a) {
+                    format!("$b{}", a)
+                } else {
+                    "$bad".to_string()
+                }
+            })
+            .collect();
+        writeln!(
+            w,
+            "        (br_table {} $bad (i32.wrap_i64 (local.get $ip))))",
+            labels.join(" ")
+        )
+        .unwrap();
+        for block in &self.blocks {
+            writeln!(w, "      ;; {}", block.start).unwrap();
+            if block.items.is_empty() {
+                writeln!(w, "      ;; {}", trap(&self.program, block.start)).unwrap();
+                w.push_str("      unreachable)\n");
+                continue;
+            }
+            for item in &block.items {
+                writeln!(w, "      ;; {}: {}", item.address(), item).unwrap();
+                writeln!(w, "      {}", wat(item)).unwrap();
+            }
+            match block.next {
+                Some(next) => writeln!(
+                    w,
+                    "      (local.set $ip (i64.const {})) (br $dispatch))",
+                    next
+                )
+                .unwrap(),
+                None => w.push_str("      unreachable)\n"),
+            }
+        }
+        w.push_str("      unreachable))\n)\n");
+        out
+    }
+}
+
+const RUST_PRELUDE: &str = "\
+fn get(m: &[i64], a: i64) -> i64 {
+    if a < 0 || a as usize >= m.len() {
+        panic!(\"Invalid address: {}\", a);
+    }
+    m[a as usize]
+}
+
+fn set(m: &mut [i64], a: i64, v: i64) {
+    if CODE.iter().any(|&(start, end)| start <= a && a < end) {
+        panic!(\"Self-modifying code: write to code at {}\", a);
+    }
+    if a < 0 || a as usize >= m.len() {
+        panic!(\"Invalid address: {}\", a);
+    }
+    m[a as usize] = v;
+}
+
+fn main() {
+    let mut text = String::new();
+    io::stdin().read_to_string(&mut text).unwrap();
+    let mut input = text
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<i64>().expect(\"Bad input\"));
+    let mut m = PROGRAM.to_vec();
+";
+
+fn decode(program: &[i64], address: usize) -> Option<Item> {
+    match disassemble_from(program, address, 1).pop() {
+        Some(item @ Item::Instruction { .. }) => Some(item),
+        _ => None,
+    }
+}
+
+fn is_jump(item: &Item) -> bool {
+    matches!(
+        item,
+        Item::Instruction { opcode: 5, .. } | Item::Instruction { opcode: 6, .. }
+    )
+}
+
+// Where an instruction can go: an immediate jump target and the next
+// instruction, if control can get there
+fn successors(item: &Item) -> (Option<usize>, Option<usize>) {
+    let next = item.address() + item.size();
+    match item {
+        Item::Instruction { opcode: 99, .. } => (None, None),
+        Item::Instruction {
+            opcode,
+            modes,
+            params,
+            ..
+        } if *opcode == 5 || *opcode == 6 => {
+            // With an immediate condition the jump is always or never taken
+            let (taken, untaken) = match modes[0] {
+                1 if (params[0] != 0) == (*opcode == 5) => (true, false),
+                1 => (false, true),
+                _ => (true, true),
+            };
+            let target = if taken && modes[1] == 1 && params[1] >= 0 {
+                Some(params[1] as usize)
+            } else {
+                None
+            };
+            (target, if untaken { Some(next) } else { None })
+        }
+        _ => (None, Some(next)),
+    }
+}
+
+// Where a jump reads its target from when it isn't immediate: a fixed
+// address, or anywhere on the stack
+fn jump_source(item: &Item) -> Option<Source> {
+    match item {
+        Item::Instruction {
+            opcode,
+            modes,
+            params,
+            ..
+        } if *opcode == 5 || *opcode == 6 => match modes[1] {
+            0 => Some(Source::Position(params[1])),
+            2 => Some(Source::Stack),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// A constant written by `add` or `mul` with two immediate operands, which
+// may be a return address, and where it's written
+fn stored_constant(item: &Item) -> Option<(usize, Source)> {
+    match item {
+        Item::Instruction {
+            opcode,
+            modes,
+            params,
+            ..
+        } if (*opcode == 1 || *opcode == 2) && modes[0] == 1 && modes[1] == 1 => {
+            let value = if *opcode == 1 {
+                params[0].checked_add(params[1])
+            } else {
+                params[0].checked_mul(params[1])
+            }?;
+            let destination = match modes[2] {
+                2 => Source::Stack,
+                _ => Source::Position(params[2]),
+            };
+            if value >= 0 {
+                Some((value as usize, destination))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+// The address written through a position-mode operand, if any
+fn static_write(item: &Item) -> Option<usize> {
+    if let Item::Instruction {
+        opcode,
+        modes,
+        params,
+        ..
+    } = item
+    {
+        let target = MNEMONICS.iter().find(|m| m.1 == *opcode as i64)?.3?;
+        if modes[target] == 0 && params[target] >= 0 {
+            return Some(params[target] as usize);
+        }
+    }
+    None
+}
+
+fn trap(program: &[i64], address: usize) -> String {
+    let cell = program.get(address).cloned().unwrap_or(0);
+    format!("Unrecognised opcode: {}, ip={}", cell % 100, address)
+}
+
+// Rust for one instruction, reading operands with `get` and writing with `set`
+fn rust(item: &Item) -> String {
+    let (opcode, modes, params) = match item {
+        Item::Instruction {
+            opcode,
+            modes,
+            params,
+            ..
+        } => (*opcode, modes, params),
+        Item::Data { .. } => unreachable!(),
+    };
+    let address = |i: usize| match modes[i] {
+        2 => format!("base + {}", params[i]),
+        _ => params[i].to_string(),
+    };
+    let read = |i: usize| match modes[i] {
+        1 => format!("({})", params[i]),
+        _ => format!("get(&m, {})", address(i)),
+    };
+    let store = |i: usize, value: String| {
+        format!("{{ let v = {}; set(&mut m, {}, v); }}", value, address(i))
+    };
+    match opcode {
+        1 => store(2, format!("{} + {}", read(0), read(1))),
+        2 => store(2, format!("{} * {}", read(0), read(1))),
+        3 => store(0, "input.next().expect(\"Out of input\")".to_string()),
+        4 => format!("println!(\"{{}}\", {});", read(0)),
+        5 => format!("if {} != 0 {{ ip = {}; continue; }}", read(0), read(1)),
+        6 => format!("if {} == 0 {{ ip = {}; continue; }}", read(0), read(1)),
+        7 => store(2, format!("({} < {}) as i64", read(0), read(1))),
+        8 => store(2, format!("({} == {}) as i64", read(0), read(1))),
+        9 => format!("base += {};", read(0)),
+        _ => "return;".to_string(),
+    }
+}
+
+// WebAssembly for one instruction
+fn wat(item: &Item) -> String {
+    let (opcode, modes, params) = match item {
+        Item::Instruction {
+            opcode,
+            modes,
+            params,
+            ..
+        } => (*opcode, modes, params),
+        Item::Data { .. } => unreachable!(),
+    };
+    let address = |i: usize| match modes[i] {
+        2 => format!("(i64.add (local.get $base) (i64.const {}))", params[i]),
+        _ => format!("(i64.const {})", params[i]),
+    };
+    let read = |i: usize| match modes[i] {
+        1 => format!("(i64.const {})", params[i]),
+        _ => format!("(call $get {})", address(i)),
+    };
+    let store = |value: String| format!("(call $set {} {})", address(2), value);
+    let compare = |op: &str| {
+        store(format!(
+            "(i64.extend_i32_u ({} {} {}))",
+            op,
+            read(0),
+            read(1)
+        ))
+    };
+    let jump = |condition: String| {
+        format!(
+            "(if {} (then (local.set $ip {}) (br $dispatch)))",
+            condition,
+            read(1)
+        )
+    };
+    match opcode {
+        1 => store(format!("(i64.add {} {})", read(0), read(1))),
+        2 => store(format!("(i64.mul {} {})", read(0), read(1))),
+        3 => format!("(call $set {} (call $input))", address(0)),
+        4 => format!("(call $output {})", read(0)),
+        5 => jump(format!("(i64.ne {} (i64.const 0))", read(0))),
+        6 => jump(format!("(i64.eqz {})", read(0))),
+        7 => compare("i64.lt_s"),
+        8 => compare("i64.eq"),
+        9 => format!("(local.set $base (i64.add (local.get $base) {}))", read(0)),
+        _ => "(return)".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+    use crate::{read_program, Computer};
+    use std::env;
+    use std::fs;
+    use std::io::Write as _;
+    use std::process::{Command, Stdio};
+
+    #[test]
+    fn test_cfg() {
+        let source = "\
+            in n
+      loop: out n
+            add n, #-1, n
+            jt n, #loop
+            hlt
+         n: data 0";
+        let cfg = Cfg::build(&assemble("t", source).unwrap().program).unwrap();
+        let starts: Vec<(usize, usize, Option<usize>)> = cfg
+            .blocks
+            .iter()
+            .map(|b| (b.start, b.items.len(), b.next))
+            .collect();
+        assert_eq!(
+            starts,
+            vec![(0, 1, Some(2)), (2, 3, Some(11)), (11, 1, None)]
+        );
+        assert_eq!(cfg.code, vec![0..12]);
+    }
+
+    #[test]
+    fn test_self_modifying() {
+        assert_eq!(
+            Cfg::build(&[1, 0, 0, 0, 99]),
+            Err(ExportError::SelfModifying { ip: 0, address: 0 })
+        );
+        // A jump to a bad opcode is only an error if it's reached
+        let cfg = Cfg::build(&[1106, 0, 4, 99, 42]).unwrap();
+        assert_eq!(cfg.blocks[1].start, 4);
+        assert!(cfg.blocks[1].items.is_empty());
+    }
+
+    #[test]
+    fn test_wat() {
+        let cfg = Cfg::build(&[3, 9, 104, 5, 1005, 9, 0, 99, 0, 0]).unwrap();
+        let wat = cfg.to_wat("t");
+        assert!(wat.contains("(br_table $b0 $bad $bad $bad $bad $bad $bad $b7 $bad (i32"));
+        assert!(wat.contains("(call $set (i64.const 9) (call $input))"));
+        assert!(wat.contains(
+            "(if (i64.ne (call $get (i64.const 9)) (i64.const 0)) (then (local.set $ip (i64.const 0)) (br $dispatch)))"
+        ));
+        assert_eq!(wat.matches('(').count(), wat.matches(')').count());
+    }
+
+    // Compiles the Rust translation and checks it against the interpreter
+    fn check_rust(name: &str, path: &str, inputs: &[i64]) {
+        let program = read_program(path).unwrap();
+        let source = Cfg::build(&program).unwrap().to_rust(path);
+        let dir = env::temp_dir().join(format!("intcode-export-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("main.rs");
+        let binary = dir.join("main");
+        fs::write(&file, source).unwrap();
+        let status = Command::new(env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string()))
+            .arg("-O")
+            .arg("-o")
+            .arg(&binary)
+            .arg(&file)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        for input in inputs {
+            let mut cpu = Computer::new(program.clone());
+            cpu.push_input(*input);
+            cpu.run();
+            let expected: Vec<String> = cpu.output.iter().map(i64::to_string).collect();
+
+            let mut child = Command::new(&binary)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .spawn()
+                .unwrap();
+            writeln!(child.stdin.take().unwrap(), "{}", input).unwrap();
+            let output = child.wait_with_output().unwrap();
+            assert!(output.status.success());
+            let actual: Vec<String> = String::from_utf8(output.stdout)
+                .unwrap()
+                .lines()
+                .map(str::to_string)
+                .collect();
+            assert_eq!(actual, expected, "{} with input {}", path, input);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_day05() {
+        // day05 patches its own code with its input
+        let program = read_program("../day05/data/input.txt").unwrap();
+        assert_eq!(
+            Cfg::build(&program),
+            Err(ExportError::SelfModifying { ip: 2, address: 6 })
+        );
+    }
+
+    #[test]
+    fn test_rust_day09() {
+        check_rust("day09", "../day09/data/input.txt", &[1, 2]);
+    }
+}
@@ -0,0 +1,897 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::fs;
+
+use crate::asm::{assemble, Assembly};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompileError {
+    pub source: String,
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.source, self.line, self.message)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Number(i64),
+    Ident(String),
+    Symbol(&'static str),
+}
+
+// Longest first, so `<=` isn't read as `<` then `=`
+const SYMBOLS: [&str; 17] = [
+    "==", "!=", "<=", ">=", "(", ")", "{", "}", ",", ";", "=", "<", ">", "+", "-", "*", "!",
+];
+
+const KEYWORDS: [&str; 6] = ["fn", "let", "if", "else", "while", "return"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    Number(i64),
+    Var(String, usize),
+    Call(String, Vec<Expr>, usize),
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Stmt {
+    Let(String, Expr),
+    Assign(String, Expr),
+    If(Expr, Vec<Statement>, Vec<Statement>),
+    While(Expr, Vec<Statement>),
+    Return(Option<Expr>),
+    Expr(Expr),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Statement {
+    line: usize,
+    stmt: Stmt,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Function {
+    name: String,
+    params: Vec<String>,
+    body: Vec<Statement>,
+    line: usize,
+}
+
+pub fn compile_file(path: &str) -> Result<Assembly, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    compile(path, &text).map_err(|e| e.to_string())
+}
+
+/// A small expression language that compiles to Intcode, by way of the
+/// assembler:
+///
+/// ```text
+/// // print the first n Fibonacci numbers
+/// fn fib(n) {
+///     if n < 2 { return n; }
+///     return fib(n - 1) + fib(n - 2);
+/// }
+///
+/// fn main() {
+///     let n = read();
+///     let i = 0;
+///     while i < n {
+///         write(fib(i));
+///         i = i + 1;
+///     }
+/// }
+/// ```
+///
+/// Every value is an integer; conditions are true when non-zero. Operators
+/// are `* + - < <= > >= == !=` and unary `-` and `!`. `read()` takes the next
+/// input and `write(x)` outputs `x`. Execution starts at `main`.
+///
+/// Each call gets a frame on a stack after the program, addressed through
+/// the relative base: the return address at `rb`, then the parameters, the
+/// locals and scratch cells for expressions. Return values go through a
+/// global cell.
+///
+/// `compile` turns `text` into an assembled program whose debug info points
+/// at the original source, naming it `source`.
+pub fn compile(source: &str, text: &str) -> Result<Assembly, CompileError> {
+    let (asm, lines) = generate(source, text)?;
+    let mut assembly = assemble(source, &asm).map_err(|e| CompileError {
+        source: source.to_string(),
+        line: lines[e.line - 1],
+        message: format!("internal error: {}", e.message),
+    })?;
+
+    // Point the debug info at the source, keeping only function labels
+    let debug = &mut assembly.debug;
+    debug.lines = debug
+        .lines
+        .iter()
+        .map(|(address, line)| (*address, lines[line - 1]))
+        .collect();
+    debug.labels = debug
+        .labels
+        .iter()
+        .filter_map(|(name, address)| Some((name.strip_prefix("f_")?.to_string(), *address)))
+        .collect();
+    Ok(assembly)
+}
+
+/// Compiles `text` to assembly source, for inspection.
+pub fn to_asm(source: &str, text: &str) -> Result<String, CompileError> {
+    Ok(generate(source, text)?.0)
+}
+
+// Assembly text, and the source line of each assembly line
+fn generate(source: &str, text: &str) -> Result<(String, Vec<usize>), CompileError> {
+    let tokens = lex(source, text)?;
+    let functions = Parser {
+        source,
+        tokens,
+        pos: 0,
+    }
+    .program()?;
+
+    let error = |line: usize, message: String| CompileError {
+        source: source.to_string(),
+        line,
+        message,
+    };
+    let mut arity = HashMap::new();
+    for f in &functions {
+        if arity.insert(f.name.clone(), f.params.len()).is_some() {
+            return Err(error(f.line, format!("duplicate function: {}", f.name)));
+        }
+    }
+    let main = functions
+        .iter()
+        .find(|f| f.name == "main")
+        .ok_or_else(|| error(1, "no main function".to_string()))?;
+    if !main.params.is_empty() {
+        return Err(error(main.line, "main takes no parameters".to_string()));
+    }
+
+    let mut out = Output::default();
+    out.emit(main.line, "arb #__stack");
+    out.emit(main.line, "add #__end, #0, rb");
+    out.emit(main.line, "jt #1, #f_main");
+    out.emit(main.line, "__end: hlt");
+    for f in &functions {
+        Generator::new(source, f, &arity, &mut out).function()?;
+    }
+    let last = out.lines.last().cloned().unwrap_or(1);
+    out.emit(last, "__ret: data 0");
+    out.emit(last, "__stack: data 0");
+    Ok((out.text.join("\n") + "\n", out.lines))
+}
+
+fn lex(source: &str, text: &str) -> Result<Vec<(Token, usize)>, CompileError> {
+    let mut tokens = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let number = i + 1;
+        let mut rest = line.split("//").next().unwrap().trim_start();
+        while !rest.is_empty() {
+            let c = rest.chars().next().unwrap();
+            let len = if c.is_ascii_digit() {
+                let len = rest
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(rest.len());
+                let n = rest[..len].parse().map_err(|e| CompileError {
+                    source: source.to_string(),
+                    line: number,
+                    message: format!("{}: {}", &rest[..len], e),
+                })?;
+                tokens.push((Token::Number(n), number));
+                len
+            } else if c.is_ascii_alphabetic() || c == '_' {
+                let len = rest
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .unwrap_or(rest.len());
+                tokens.push((Token::Ident(rest[..len].to_string()), number));
+                len
+            } else if let Some(s) = SYMBOLS.iter().find(|s| rest.starts_with(*s)) {
+                tokens.push((Token::Symbol(s), number));
+                s.len()
+            } else {
+                return Err(CompileError {
+                    source: source.to_string(),
+                    line: number,
+                    message: format!("unexpected character: {}", c),
+                });
+            };
+            rest = rest[len..].trim_start();
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .or_else(|| self.tokens.last())
+            .map(|t| t.1)
+            .unwrap_or(1)
+    }
+
+    fn error<T>(&self, message: String) -> Result<T, CompileError> {
+        Err(CompileError {
+            source: self.source.to_string(),
+            line: self.line(),
+            message,
+        })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|t| &t.0)
+    }
+
+    fn next(&mut self) -> Result<Token, CompileError> {
+        match self.tokens.get(self.pos) {
+            Some((token, _)) => {
+                self.pos += 1;
+                Ok(token.clone())
+            }
+            None => self.error("unexpected end of input".to_string()),
+        }
+    }
+
+    fn is(&self, symbol: &str) -> bool {
+        matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol)
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(s)) if s == keyword)
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<(), CompileError> {
+        match self.next()? {
+            Token::Symbol(s) if s == symbol => Ok(()),
+            token => {
+                self.pos -= 1;
+                self.error(format!("expected '{}', found {}", symbol, describe(&token)))
+            }
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str) -> Result<(), CompileError> {
+        if !self.is_keyword(keyword) {
+            let found = self
+                .peek()
+                .map(describe)
+                .unwrap_or_else(|| "end".to_string());
+            return self.error(format!("expected '{}', found {}", keyword, found));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn ident(&mut self) -> Result<String, CompileError> {
+        match self.next()? {
+            Token::Ident(name) if !KEYWORDS.contains(&name.as_str()) => {
+                if name.starts_with("__") {
+                    self.pos -= 1;
+                    return self.error(format!("reserved name: {}", name));
+                }
+                Ok(name)
+            }
+            token => {
+                self.pos -= 1;
+                self.error(format!("expected a name, found {}", describe(&token)))
+            }
+        }
+    }
+
+    fn program(&mut self) -> Result<Vec<Function>, CompileError> {
+        let mut functions = Vec::new();
+        while self.peek().is_some() {
+            let line = self.line();
+            self.keyword("fn")?;
+            let name = self.ident()?;
+            self.expect("(")?;
+            let mut params = Vec::new();
+            while !self.is(")") {
+                if !params.is_empty() {
+                    self.expect(",")?;
+                }
+                params.push(self.ident()?);
+            }
+            self.expect(")")?;
+            let body = self.block()?;
+            functions.push(Function {
+                name,
+                params,
+                body,
+                line,
+            });
+        }
+        Ok(functions)
+    }
+
+    fn block(&mut self) -> Result<Vec<Statement>, CompileError> {
+        self.expect("{")?;
+        let mut statements = Vec::new();
+        while !self.is("}") {
+            statements.push(self.statement()?);
+        }
+        self.expect("}")?;
+        Ok(statements)
+    }
+
+    fn statement(&mut self) -> Result<Statement, CompileError> {
+        let line = self.line();
+        let stmt = if self.is_keyword("let") {
+            self.pos += 1;
+            let name = self.ident()?;
+            self.expect("=")?;
+            let value = self.expr()?;
+            self.expect(";")?;
+            Stmt::Let(name, value)
+        } else if self.is_keyword("if") {
+            self.pos += 1;
+            let condition = self.expr()?;
+            let then = self.block()?;
+            let otherwise = if self.is_keyword("else") {
+                self.pos += 1;
+                if self.is_keyword("if") {
+                    vec![self.statement()?]
+                } else {
+                    self.block()?
+                }
+            } else {
+                Vec::new()
+            };
+            Stmt::If(condition, then, otherwise)
+        } else if self.is_keyword("while") {
+            self.pos += 1;
+            let condition = self.expr()?;
+            Stmt::While(condition, self.block()?)
+        } else if self.is_keyword("return") {
+            self.pos += 1;
+            let value = if self.is(";") {
+                None
+            } else {
+                Some(self.expr()?)
+            };
+            self.expect(";")?;
+            Stmt::Return(value)
+        } else if matches!(self.tokens.get(self.pos + 1), Some((Token::Symbol("="), _))) {
+            let name = self.ident()?;
+            self.expect("=")?;
+            let value = self.expr()?;
+            self.expect(";")?;
+            Stmt::Assign(name, value)
+        } else {
+            let value = self.expr()?;
+            self.expect(";")?;
+            Stmt::Expr(value)
+        };
+        Ok(Statement { line, stmt })
+    }
+
+    fn expr(&mut self) -> Result<Expr, CompileError> {
+        let left = self.sum()?;
+        let op = match self.peek() {
+            Some(Token::Symbol("<")) => BinOp::Lt,
+            Some(Token::Symbol("<=")) => BinOp::Le,
+            Some(Token::Symbol(">")) => BinOp::Gt,
+            Some(Token::Symbol(">=")) => BinOp::Ge,
+            Some(Token::Symbol("==")) => BinOp::Eq,
+            Some(Token::Symbol("!=")) => BinOp::Ne,
+            _ => return Ok(left),
+        };
+        self.pos += 1;
+        let right = self.sum()?;
+        Ok(Expr::Binary(op, Box::new(left), Box::new(right)))
+    }
+
+    fn sum(&mut self) -> Result<Expr, CompileError> {
+        let mut left = self.product()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Symbol("+")) => BinOp::Add,
+                Some(Token::Symbol("-")) => BinOp::Sub,
+                _ => return Ok(left),
+            };
+            self.pos += 1;
+            let right = self.product()?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+    }
+
+    fn product(&mut self) -> Result<Expr, CompileError> {
+        let mut left = self.unary()?;
+        while self.is("*") {
+            self.pos += 1;
+            let right = self.unary()?;
+            left = Expr::Binary(BinOp::Mul, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, CompileError> {
+        if self.is("-") {
+            self.pos += 1;
+            return Ok(match self.unary()? {
+                Expr::Number(n) => Expr::Number(-n),
+                e => Expr::Neg(Box::new(e)),
+            });
+        }
+        if self.is("!") {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, CompileError> {
+        let line = self.line();
+        if self.is("(") {
+            self.pos += 1;
+            let e = self.expr()?;
+            self.expect(")")?;
+            return Ok(e);
+        }
+        if let Some(Token::Number(n)) = self.peek() {
+            let n = *n;
+            self.pos += 1;
+            return Ok(Expr::Number(n));
+        }
+        let name = self.ident()?;
+        if !self.is("(") {
+            return Ok(Expr::Var(name, line));
+        }
+        self.pos += 1;
+        let mut args = Vec::new();
+        while !self.is(")") {
+            if !args.is_empty() {
+                self.expect(",")?;
+            }
+            args.push(self.expr()?);
+        }
+        self.expect(")")?;
+        Ok(Expr::Call(name, args, line))
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Number(n) => n.to_string(),
+        Token::Ident(name) => format!("'{}'", name),
+        Token::Symbol(s) => format!("'{}'", s),
+    }
+}
+
+#[derive(Default)]
+struct Output {
+    text: Vec<String>,
+    lines: Vec<usize>,
+    labels: usize,
+}
+
+impl Output {
+    fn emit(&mut self, line: usize, text: &str) {
+        self.text.push(text.to_string());
+        self.lines.push(line);
+    }
+
+    fn label(&mut self, kind: &str) -> String {
+        self.labels += 1;
+        format!("__{}{}", kind, self.labels)
+    }
+}
+
+// The frame size isn't known until the whole function is generated, so
+// operands past the end of the frame are written `{frame}` or `{frame+n}`
+// and filled in afterwards
+const FRAME: &str = "{frame";
+
+fn fill_frame(text: &str, frame: usize) -> String {
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(FRAME) {
+        let end = start + rest[start..].find('}').unwrap();
+        let offset: usize = rest[start + FRAME.len()..end]
+            .strip_prefix('+')
+            .map(|n| n.parse().unwrap())
+            .unwrap_or(0);
+        result.push_str(&rest[..start]);
+        result.push_str(&(frame + offset).to_string());
+        rest = &rest[end + 1..];
+    }
+    result + rest
+}
+
+struct Generator<'a> {
+    source: &'a str,
+    function: &'a Function,
+    arity: &'a HashMap<String, usize>,
+    out: &'a mut Output,
+    start: usize,
+    slots: BTreeMap<String, usize>,
+    declared: HashSet<String>,
+    locals: usize,
+    temps: usize,
+    max_temps: usize,
+}
+
+impl<'a> Generator<'a> {
+    fn new(
+        source: &'a str,
+        function: &'a Function,
+        arity: &'a HashMap<String, usize>,
+        out: &'a mut Output,
+    ) -> Generator<'a> {
+        let start = out.text.len();
+        Generator {
+            source,
+            function,
+            arity,
+            out,
+            start,
+            slots: BTreeMap::new(),
+            declared: HashSet::new(),
+            locals: 0,
+            temps: 0,
+            max_temps: 0,
+        }
+    }
+
+    fn error<T>(&self, line: usize, message: String) -> Result<T, CompileError> {
+        Err(CompileError {
+            source: self.source.to_string(),
+            line,
+            message,
+        })
+    }
+
+    fn function(mut self) -> Result<(), CompileError> {
+        let f = self.function;
+        // Slot 0 holds the return address
+        for (i, param) in f.params.iter().enumerate() {
+            if self.slots.insert(param.clone(), i + 1).is_some() {
+                return self.error(f.line, format!("duplicate parameter: {}", param));
+            }
+            self.declared.insert(param.clone());
+        }
+        self.locals = f.params.len() + 1;
+        collect_locals(&f.body, &mut |name| {
+            if !self.slots.contains_key(name) {
+                self.slots.insert(name.to_string(), self.locals);
+                self.locals += 1;
+            }
+        });
+
+        self.out.emit(f.line, &format!("f_{}:", f.name));
+        self.block(&f.body)?;
+        let end = f.body.last().map(|s| s.line).unwrap_or(f.line);
+        self.out.emit(end, "add #0, #0, __ret");
+        self.out.emit(end, "jt #1, rb");
+
+        let frame = self.locals + self.max_temps;
+        for text in &mut self.out.text[self.start..] {
+            *text = fill_frame(text, frame);
+        }
+        Ok(())
+    }
+
+    fn block(&mut self, statements: &[Statement]) -> Result<(), CompileError> {
+        for s in statements {
+            self.temps = 0;
+            self.statement(s)?;
+        }
+        Ok(())
+    }
+
+    fn emit(&mut self, line: usize, text: String) {
+        self.out.emit(line, &text);
+    }
+
+    fn temp(&mut self) -> String {
+        let slot = self.locals + self.temps;
+        self.temps += 1;
+        self.max_temps = self.max_temps.max(self.temps);
+        format!("rb+{}", slot)
+    }
+
+    fn variable(&self, name: &str, line: usize) -> Result<String, CompileError> {
+        if !self.declared.contains(name) {
+            return self.error(line, format!("undeclared variable: {}", name));
+        }
+        Ok(format!("rb+{}", self.slots[name]))
+    }
+
+    fn statement(&mut self, s: &Statement) -> Result<(), CompileError> {
+        let line = s.line;
+        match &s.stmt {
+            Stmt::Let(name, value) => {
+                if self.declared.contains(name) {
+                    return self.error(line, format!("variable already declared: {}", name));
+                }
+                let value = self.expr(value, line)?;
+                self.declared.insert(name.clone());
+                let slot = self.variable(name, line)?;
+                self.emit(line, format!("add {}, #0, {}", value, slot));
+            }
+            Stmt::Assign(name, value) => {
+                let slot = self.variable(name, line)?;
+                let value = self.expr(value, line)?;
+                self.emit(line, format!("add {}, #0, {}", value, slot));
+            }
+            Stmt::If(condition, then, otherwise) => {
+                let (other, end) = (self.out.label("else"), self.out.label("endif"));
+                let condition = self.expr(condition, line)?;
+                self.emit(line, format!("jf {}, #{}", condition, other));
+                self.block(then)?;
+                self.emit(line, format!("jt #1, #{}", end));
+                self.emit(line, format!("{}:", other));
+                self.block(otherwise)?;
+                self.emit(line, format!("{}:", end));
+            }
+            Stmt::While(condition, body) => {
+                let (top, end) = (self.out.label("while"), self.out.label("wend"));
+                self.emit(line, format!("{}:", top));
+                let condition = self.expr(condition, line)?;
+                self.emit(line, format!("jf {}, #{}", condition, end));
+                self.block(body)?;
+                self.emit(line, format!("jt #1, #{}", top));
+                self.emit(line, format!("{}:", end));
+            }
+            Stmt::Return(value) => {
+                let value = match value {
+                    Some(value) => self.expr(value, line)?,
+                    None => "#0".to_string(),
+                };
+                self.emit(line, format!("add {}, #0, __ret", value));
+                self.emit(line, "jt #1, rb".to_string());
+            }
+            Stmt::Expr(value) => {
+                self.expr(value, line)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Generates code for `e`, returning the operand that holds its value
+    fn expr(&mut self, e: &Expr, line: usize) -> Result<String, CompileError> {
+        Ok(match e {
+            Expr::Number(n) => format!("#{}", n),
+            Expr::Var(name, line) => self.variable(name, *line)?,
+            Expr::Neg(e) => {
+                let x = self.expr(e, line)?;
+                let t = self.temp();
+                self.emit(line, format!("mul {}, #-1, {}", x, t));
+                t
+            }
+            Expr::Not(e) => {
+                let x = self.expr(e, line)?;
+                let t = self.temp();
+                self.emit(line, format!("eq {}, #0, {}", x, t));
+                t
+            }
+            Expr::Binary(op, left, right) => {
+                let x = self.expr(left, line)?;
+                let y = self.expr(right, line)?;
+                let t = self.temp();
+                let (mnemonic, x, y, negate) = match op {
+                    BinOp::Add => ("add", x, y, false),
+                    BinOp::Mul => ("mul", x, y, false),
+                    BinOp::Sub => {
+                        let y = match y.strip_prefix('#') {
+                            Some(n) => format!("#{}", -n.parse::<i64>().unwrap()),
+                            None => {
+                                self.emit(line, format!("mul {}, #-1, {}", y, t));
+                                t.clone()
+                            }
+                        };
+                        ("add", x, y, false)
+                    }
+                    BinOp::Lt => ("lt", x, y, false),
+                    BinOp::Gt => ("lt", y, x, false),
+                    BinOp::Le => ("lt", y, x, true),
+                    BinOp::Ge => ("lt", x, y, true),
+                    BinOp::Eq => ("eq", x, y, false),
+                    BinOp::Ne => ("eq", x, y, true),
+                };
+                self.emit(line, format!("{} {}, {}, {}", mnemonic, x, y, t));
+                if negate {
+                    self.emit(line, format!("eq {}, #0, {}", t, t));
+                }
+                t
+            }
+            Expr::Call(name, args, line) => self.call(name, args, *line)?,
+        })
+    }
+
+    fn call(&mut self, name: &str, args: &[Expr], line: usize) -> Result<String, CompileError> {
+        match (name, args) {
+            ("read", []) => {
+                let t = self.temp();
+                self.emit(line, format!("in {}", t));
+                return Ok(t);
+            }
+            ("write", [value]) => {
+                let value = self.expr(value, line)?;
+                self.emit(line, format!("out {}", value));
+                return Ok("#0".to_string());
+            }
+            ("read", _) | ("write", _) => {
+                return self.error(line, format!("wrong number of arguments to {}", name));
+            }
+            _ => (),
+        }
+        match self.arity.get(name) {
+            None => return self.error(line, format!("unknown function: {}", name)),
+            Some(n) if *n != args.len() => {
+                return self.error(
+                    line,
+                    format!("{} takes {} arguments, got {}", name, n, args.len()),
+                );
+            }
+            _ => (),
+        }
+
+        // Evaluate every argument before filling in the callee's frame, as
+        // nested calls would overwrite it
+        let values = args
+            .iter()
+            .map(|a| self.expr(a, line))
+            .collect::<Result<Vec<_>, _>>()?;
+        for (i, value) in values.iter().enumerate() {
+            self.emit(line, format!("add {}, #0, rb+{{frame+{}}}", value, i + 1));
+        }
+        let back = self.out.label("back");
+        self.emit(line, format!("add #{}, #0, rb+{{frame}}", back));
+        self.emit(line, "arb #{frame}".to_string());
+        self.emit(line, format!("jt #1, #f_{}", name));
+        self.emit(line, format!("{}: arb #-{{frame}}", back));
+        let t = self.temp();
+        self.emit(line, format!("add __ret, #0, {}", t));
+        Ok(t)
+    }
+}
+
+fn collect_locals<F: FnMut(&str)>(statements: &[Statement], f: &mut F) {
+    for s in statements {
+        match &s.stmt {
+            Stmt::Let(name, _) => f(name),
+            Stmt::If(_, then, otherwise) => {
+                collect_locals(then, f);
+                collect_locals(otherwise, f);
+            }
+            Stmt::While(_, body) => collect_locals(body, f),
+            _ => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::machine::Engine;
+
+    const FIB: &str = "\
+// print the first n Fibonacci numbers
+fn fib(n) {
+    if n < 2 { return n; }
+    return fib(n - 1) + fib(n - 2);
+}
+
+fn main() {
+    let n = read();
+    let i = 0;
+    while i < n {
+        write(fib(i));
+        i = i + 1;
+    }
+}
+";
+
+    fn run(text: &str, input: &[i64]) -> Vec<i64> {
+        let program = compile("test.ic", text).unwrap().program;
+        let mut outputs = Vec::new();
+        for engine in Engine::ALL.iter() {
+            let mut machine = engine.machine(&program);
+            input.iter().for_each(|v| machine.push_input(*v));
+            machine.run().unwrap();
+            outputs.push(machine.take_output());
+        }
+        assert!(outputs.windows(2).all(|w| w[0] == w[1]));
+        outputs.pop().unwrap()
+    }
+
+    #[test]
+    fn test_fib() {
+        assert_eq!(run(FIB, &[10]), vec![0, 1, 1, 2, 3, 5, 8, 13, 21, 34]);
+    }
+
+    #[test]
+    fn test_operators() {
+        let text = "fn main() {
+            let a = read();
+            let b = read();
+            write(a + b * 2 - -3);
+            write(a - b);
+            write(-(a - b));
+            write((a < b) + (a <= b) * 10 + (a > b) * 100 + (a >= b) * 1000);
+            write((a == b) + (a != b) * 10 + !a * 100 + !!b * 1000);
+        }";
+        assert_eq!(run(text, &[5, 7]), vec![22, -2, 2, 11, 1010]);
+        assert_eq!(run(text, &[7, 7]), vec![24, 0, 0, 1010, 1001]);
+    }
+
+    #[test]
+    fn test_calls() {
+        let text = "
+            fn max(a, b) { if a > b { return a; } else { return b; } }
+            fn sum3(a, b, c) { return a + b + c; }
+            fn nothing() { }
+            fn fact(n) { if n == 0 { return 1; } return n * fact(n - 1); }
+            fn main() {
+                write(max(3, 9) + max(sum3(1, 2, max(4, 1)), 2));
+                write(nothing());
+                write(fact(10));
+                let i = 0;
+                while i < 3 { let j = i * i; write(j); i = i + 1; }
+            }";
+        assert_eq!(run(text, &[]), vec![16, 0, 3628800, 0, 1, 4]);
+    }
+
+    #[test]
+    fn test_debug_info() {
+        let debug = compile("fib.ic", FIB).unwrap().debug;
+        let fib = debug.labels["fib"];
+        assert_eq!(debug.location(fib), "fib (fib.ic:3)");
+        assert_eq!(debug.labels.len(), 2);
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = |text| compile("bad.ic", text).unwrap_err().to_string();
+        assert_eq!(err("fn f() {}"), "bad.ic:1: no main function");
+        assert_eq!(
+            err("fn main() {\n x = 1;\n}"),
+            "bad.ic:2: undeclared variable: x"
+        );
+        assert_eq!(
+            err("fn main() {\n let x = 1;\n let x = 2;\n}"),
+            "bad.ic:3: variable already declared: x"
+        );
+        assert_eq!(err("fn main() { f(1); }"), "bad.ic:1: unknown function: f");
+        assert_eq!(
+            err("fn f(a) {} fn main() { f(); }"),
+            "bad.ic:1: f takes 1 arguments, got 0"
+        );
+        assert_eq!(
+            err("fn main() { write(1) }"),
+            "bad.ic:1: expected ';', found '}'"
+        );
+        assert_eq!(err("fn main() { # }"), "bad.ic:1: unexpected character: #");
+    }
+}
@@ -0,0 +1,580 @@
+pub mod asm;
+pub mod compiled;
+pub mod debug;
+pub mod decoded;
+pub mod diff;
+pub mod disasm;
+pub mod export;
+pub mod lang;
+pub mod loops;
+pub mod machine;
+pub mod memory;
+pub mod options;
+pub mod patch;
+pub mod repl;
+pub mod search;
+pub mod session;
+pub mod solver;
+pub mod stats;
+pub mod symbolic;
+pub mod tui;
+pub mod watch;
+
+use std::error;
+use std::fmt;
+use std::fs;
+use std::num::ParseIntError;
+use std::str::FromStr;
+use std::time::Instant;
+
+use crate::debug::DebugInfo;
+use crate::loops::LoopDetector;
+use crate::memory::Memory;
+use crate::session::{Event, Session};
+use crate::stats::Stats;
+use crate::watch::{Access, Watch};
+
+#[derive(Clone)]
+pub struct Computer {
+    pub ip: i64,
+    // Grows when the program writes past its end; other cells past the end
+    // read as zero
+    pub memory: Memory,
+    pub memory_orig: Memory,
+    pub input: Vec<i64>,
+    pub output: Vec<i64>,
+    pub halted: bool,
+    pub base: i64,
+    // Number of instructions executed since the last reset
+    pub steps: u64,
+    pub session: Option<Session>,
+    // Value of `steps` when recording started; session events count from it
+    recorded_from: u64,
+    pub watch: Option<Watch>,
+    // Fail with `Error::Overflow` instead of wrapping or panicking
+    pub checked: bool,
+    // Source locations for errors, when the program was assembled
+    pub debug: Option<DebugInfo>,
+    pub stats: Option<Stats>,
+    pub loops: Option<LoopDetector>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ready,
+    AwaitingInput,
+    Halted,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    UnknownOpcode { opcode: i64, ip: i64 },
+    UnknownMode { mode: u8, ip: i64 },
+    InvalidAddress { address: i64, ip: i64 },
+    Overflow { ip: i64 },
+    // The machine reached the same state twice, running `start..=end` in between
+    InfiniteLoop { start: i64, end: i64, ip: i64 },
+}
+
+impl Error {
+    pub fn ip(&self) -> i64 {
+        match self {
+            Error::UnknownOpcode { ip, .. }
+            | Error::UnknownMode { ip, .. }
+            | Error::InvalidAddress { ip, .. }
+            | Error::Overflow { ip }
+            | Error::InfiniteLoop { ip, .. } => *ip,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::UnknownOpcode { opcode, ip } => {
+                write!(f, "Unrecognised opcode: {}, ip={}", opcode, ip)
+            }
+            Error::UnknownMode { mode, ip } => write!(f, "Unknown mode: {}, ip={}", mode, ip),
+            Error::InvalidAddress { address, ip } => {
+                write!(f, "Invalid address: {}, ip={}", address, ip)
+            }
+            Error::Overflow { ip } => write!(f, "Arithmetic overflow, ip={}", ip),
+            Error::InfiniteLoop { start, end, ip } => write!(
+                f,
+                "Infinite loop over addresses {}..={}, ip={}",
+                start, end, ip
+            ),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl Computer {
+    pub fn new(memory: Vec<i64>) -> Computer {
+        let memory = Memory::from(memory);
+        Computer {
+            ip: 0,
+            memory: memory.clone(),
+            memory_orig: memory,
+            input: Vec::new(),
+            output: Vec::new(),
+            halted: false,
+            base: 0,
+            steps: 0,
+            session: None,
+            recorded_from: 0,
+            watch: None,
+            checked: false,
+            debug: None,
+            stats: None,
+            loops: None,
+        }
+    }
+
+    /// Makes an independent copy of the machine in its current state. The
+    /// copy shares memory pages with this one until either writes to them,
+    /// so forking mid-run to explore alternatives is cheap.
+    pub fn fork(&self) -> Computer {
+        self.clone()
+    }
+
+    pub fn reset(&mut self) {
+        self.ip = 0;
+        self.memory = self.memory_orig.clone();
+        self.input.clear();
+        self.output.clear();
+        self.halted = false;
+        self.base = 0;
+        self.steps = 0;
+        self.recorded_from = 0;
+        if let Some(session) = self.session.as_mut() {
+            session.events.clear();
+        }
+        if let Some(stats) = self.stats.as_mut() {
+            *stats = Stats::default();
+        }
+        if let Some(loops) = self.loops.as_mut() {
+            *loops = LoopDetector::new();
+        }
+    }
+
+    /// Starts recording inputs consumed, outputs produced and cells `set`
+    /// into `session`, with steps counted from now.
+    pub fn record(&mut self) {
+        self.session = Some(Session::default());
+        self.recorded_from = self.steps;
+    }
+
+    /// Writes a memory cell from outside the program, e.g. from the REPL,
+    /// and records the write so a replay can repeat it.
+    pub fn set(&mut self, address: usize, value: i64) {
+        if address >= self.memory.len() {
+            self.memory.resize(address + 1);
+        }
+        self.memory[address] = value;
+        self.log(Event::Set {
+            step: self.steps - self.recorded_from,
+            address,
+            value,
+        });
+    }
+
+    /// Starts checking for guaranteed infinite loops, which `try_run` then
+    /// reports as `Error::InfiniteLoop` instead of running forever.
+    pub fn detect_loops(&mut self) {
+        self.loops = Some(LoopDetector::new());
+    }
+
+    fn log(&mut self, event: Event) {
+        if let Some(session) = self.session.as_mut() {
+            session.events.push(event);
+        }
+    }
+
+    pub fn push_input(&mut self, value: i64) {
+        self.input.push(value);
+        if let Some(loops) = self.loops.as_mut() {
+            *loops = LoopDetector::new();
+        }
+    }
+
+    fn touch(&mut self, address: i64, access: Access, mode: u8) {
+        // The instruction fails on an invalid address, so there's no access
+        // to record
+        if self.check_address(address).is_err() {
+            return;
+        }
+        if let Some(watch) = self.watch.as_mut() {
+            watch.record(address as usize, access, mode);
+        }
+        if let Some(stats) = self.stats.as_mut() {
+            stats.touched(address as usize);
+        }
+    }
+
+    fn peek(&mut self, address: i64, mode: u8) -> Result<i64, Error> {
+        let address = self.get_address(address, mode)?;
+        if mode != 1 {
+            self.touch(address, Access::Read, mode);
+        }
+        self.load(address)
+    }
+
+    fn poke(&mut self, address: i64, value: i64, mode: u8) -> Result<i64, Error> {
+        let address = self.get_address(address, mode)?;
+        self.touch(address, Access::Write, mode);
+        self.check_address(address)?;
+        if address as usize >= self.memory.len() {
+            self.memory.resize(address as usize + 1);
+        }
+        self.memory[address as usize] = value;
+        Ok(address)
+    }
+
+    fn get_address(&mut self, address: i64, mode: u8) -> Result<i64, Error> {
+        self.touch(address, Access::Exec, mode);
+        match mode {
+            0 => self.load(address),
+            1 => Ok(address),
+            2 => Ok(self.load(address)? + self.base),
+            mode => Err(Error::UnknownMode { mode, ip: self.ip }),
+        }
+    }
+
+    fn load(&self, address: i64) -> Result<i64, Error> {
+        self.check_address(address)?;
+        Ok(self.memory.get(address as usize).unwrap_or(0))
+    }
+
+    fn check_address(&self, address: i64) -> Result<(), Error> {
+        if address < 0 {
+            return Err(Error::InvalidAddress {
+                address,
+                ip: self.ip,
+            });
+        }
+        Ok(())
+    }
+
+    fn arithmetic(&self, x: i64, y: i64, opcode: i32) -> Result<i64, Error> {
+        if !self.checked {
+            return Ok(if opcode == 1 { x + y } else { x * y });
+        }
+        let result = if opcode == 1 {
+            x.checked_add(y)
+        } else {
+            x.checked_mul(y)
+        };
+        result.ok_or(Error::Overflow { ip: self.ip })
+    }
+
+    pub fn decode(instruction: i32) -> (i32, [u8; 3]) {
+        (
+            instruction % 100,
+            [
+                ((instruction / 100) % 10) as u8,
+                ((instruction / 1000) % 10) as u8,
+                ((instruction / 10000) % 10) as u8,
+            ],
+        )
+    }
+
+    pub fn run(&mut self) -> Status {
+        match self.try_run() {
+            Ok(status) => status,
+            Err(e) => panic!("{}", self.explain(&e)),
+        }
+    }
+
+    /// As `run`, but reports bad instructions and addresses as errors
+    /// rather than panicking.
+    pub fn try_run(&mut self) -> Result<Status, Error> {
+        let start = Instant::now();
+        let result = loop {
+            match self.try_step() {
+                Ok(Some(status)) => break Ok(status),
+                Ok(None) => (),
+                Err(e) => break Err(e),
+            }
+        };
+        if let Some(stats) = self.stats.as_mut() {
+            stats.elapsed += start.elapsed();
+        }
+        result
+    }
+
+    /// Executes a single instruction, returning a status if the machine
+    /// can't go any further.
+    pub fn step(&mut self) -> Option<Status> {
+        match self.try_step() {
+            Ok(status) => status,
+            Err(e) => panic!("{}", self.explain(&e)),
+        }
+    }
+
+    /// Describes an error, with its source location if there's debug info.
+    pub fn explain(&self, error: &Error) -> String {
+        match &self.debug {
+            Some(debug) if error.ip() >= 0 => {
+                format!("{} at {}", error, debug.location(error.ip() as usize))
+            }
+            _ => error.to_string(),
+        }
+    }
+
+    /// The instruction pointer, with its source location if there's debug
+    /// info.
+    pub fn location(&self) -> String {
+        match &self.debug {
+            Some(debug) if self.ip >= 0 => debug.location(self.ip as usize),
+            _ => self.ip.to_string(),
+        }
+    }
+
+    /// As `step`, but reports bad instructions and addresses as errors
+    /// rather than panicking.
+    pub fn try_step(&mut self) -> Result<Option<Status>, Error> {
+        if self.halted {
+            return Ok(Some(Status::Halted));
+        }
+        self.touch(self.ip, Access::Exec, 1);
+        if let Some(loops) = self.loops.as_mut() {
+            loops.visit(self.ip);
+        }
+        let instruction = self.load(self.ip)?;
+        let (opcode, modes) = Computer::decode(instruction as i32);
+        match opcode {
+            1 | 2 => {
+                // add, multiply
+                let x = self.peek(self.ip + 1, modes[0])?;
+                let y = self.peek(self.ip + 2, modes[1])?;
+                let value = self.arithmetic(x, y, opcode)?;
+                self.poke(self.ip + 3, value, modes[2])?;
+                self.ip += 4;
+            }
+            3 => {
+                // input
+                if self.input.is_empty() {
+                    return Ok(Some(Status::AwaitingInput));
+                }
+                let value = self.input.remove(0);
+                if let Some(stats) = self.stats.as_mut() {
+                    stats.inputs += 1;
+                }
+                self.log(Event::Input {
+                    step: self.steps - self.recorded_from,
+                    value,
+                });
+                self.poke(self.ip + 1, value, modes[0])?;
+                self.ip += 2;
+            }
+            4 => {
+                // output
+                let a = self.peek(self.ip + 1, modes[0])?;
+                self.log(Event::Output {
+                    step: self.steps - self.recorded_from,
+                    value: a,
+                });
+                self.output.push(a);
+                if let Some(stats) = self.stats.as_mut() {
+                    stats.outputs += 1;
+                }
+                self.ip += 2;
+            }
+            5 => {
+                // jump if true
+                let x = self.peek(self.ip + 1, modes[0])?;
+                let y = self.peek(self.ip + 2, modes[1])?;
+                if x != 0 {
+                    self.jump(y)?;
+                } else {
+                    self.ip += 3;
+                }
+            }
+            6 => {
+                // jump if false
+                let x = self.peek(self.ip + 1, modes[0])?;
+                let y = self.peek(self.ip + 2, modes[1])?;
+                if x == 0 {
+                    self.jump(y)?;
+                } else {
+                    self.ip += 3;
+                }
+            }
+            7 => {
+                // set less than
+                let x = self.peek(self.ip + 1, modes[0])?;
+                let y = self.peek(self.ip + 2, modes[1])?;
+                if x < y {
+                    self.poke(self.ip + 3, 1, modes[2])?;
+                } else {
+                    self.poke(self.ip + 3, 0, modes[2])?;
+                }
+                self.ip += 4;
+            }
+            8 => {
+                // set equal to
+                let x = self.peek(self.ip + 1, modes[0])?;
+                let y = self.peek(self.ip + 2, modes[1])?;
+                if x == y {
+                    self.poke(self.ip + 3, 1, modes[2])?;
+                } else {
+                    self.poke(self.ip + 3, 0, modes[2])?;
+                }
+                self.ip += 4;
+            }
+            9 => {
+                // relative base
+                let val = self.peek(self.ip + 1, modes[0])?;
+                self.base += val;
+                if let Some(stats) = self.stats.as_mut() {
+                    stats.max_base = stats.max_base.max(self.base);
+                }
+                self.ip += 2;
+            }
+            99 => {
+                self.halted = true;
+                self.ip += 1;
+                self.tick(opcode);
+                return Ok(Some(Status::Halted));
+            }
+            _ => {
+                return Err(Error::UnknownOpcode {
+                    opcode: instruction % 100,
+                    ip: self.ip,
+                })
+            }
+        }
+        self.tick(opcode);
+        Ok(None)
+    }
+
+    fn jump(&mut self, target: i64) -> Result<(), Error> {
+        let from = self.ip;
+        self.ip = target;
+        if target > from {
+            return Ok(());
+        }
+        if let Some(loops) = self.loops.as_mut() {
+            if let Some((start, end)) = loops.check(target, self.base, &self.input, &self.memory) {
+                return Err(Error::InfiniteLoop {
+                    start,
+                    end,
+                    ip: target,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn tick(&mut self, opcode: i32) {
+        self.steps += 1;
+        if let Some(stats) = self.stats.as_mut() {
+            stats.executed(opcode as i64);
+        }
+        if let Some(watch) = self.watch.as_mut() {
+            watch.tick(self.steps);
+        }
+    }
+}
+
+pub fn parse_instructions(input: &str) -> Result<Vec<i64>, ParseIntError> {
+    input
+        .trim()
+        .split(',')
+        .map(|s| i64::from_str(s.trim()))
+        .collect()
+}
+
+pub fn read_program(path: &str) -> Result<Vec<i64>, String> {
+    let input = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    parse_instructions(&input).map_err(|e| format!("{}: {}", path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::machine::Machine;
+    use conformance::Vm;
+
+    impl Vm for Computer {
+        fn load(program: Vec<i64>) -> Computer {
+            Computer::new(program)
+        }
+
+        fn push_input(&mut self, value: i64) {
+            Computer::push_input(self, value);
+        }
+
+        fn run(&mut self) {
+            Computer::run(self);
+        }
+
+        fn output(&self) -> Vec<i64> {
+            self.output.clone()
+        }
+
+        fn read(&self, address: usize) -> i64 {
+            Machine::read(self, address)
+        }
+
+        fn write(&mut self, address: usize, value: i64) {
+            Machine::write(self, address, value);
+        }
+    }
+
+    #[test]
+    fn test_conformance() {
+        let failures = conformance::run_all::<Computer>();
+        assert!(failures.is_empty(), "{:#?}", failures);
+    }
+
+    #[test]
+    fn test_parse_instructions() {
+        assert_eq!(
+            parse_instructions("1,0,-3,99\n").unwrap(),
+            vec![1, 0, -3, 99]
+        );
+        assert!(parse_instructions("1,x,99").is_err());
+    }
+
+    #[test]
+    fn test_run() {
+        let mut cpu = Computer::new(vec![109, 1, 203, 2, 204, 2, 99]);
+        cpu.push_input(555);
+        assert_eq!(cpu.run(), Status::Halted);
+        assert_eq!(cpu.output, vec![555]);
+    }
+
+    #[test]
+    fn test_fork() {
+        // Adds each input to a running total and outputs it
+        let mut cpu = Computer::new(vec![3, 100, 1, 100, 101, 101, 4, 101, 1105, 1, 0]);
+        cpu.push_input(5);
+        assert_eq!(cpu.run(), Status::AwaitingInput);
+
+        let mut child = cpu.fork();
+        assert_eq!(child.memory.shared_pages(&cpu.memory), cpu.memory.pages());
+        child.push_input(10);
+        assert_eq!(child.run(), Status::AwaitingInput);
+        assert_eq!(child.output, vec![5, 15]);
+        assert_eq!(
+            child.memory.shared_pages(&cpu.memory),
+            cpu.memory.pages() - 1
+        );
+
+        cpu.push_input(1);
+        assert_eq!(cpu.run(), Status::AwaitingInput);
+        assert_eq!(cpu.output, vec![5, 6]);
+    }
+
+    #[test]
+    fn test_awaiting_input() {
+        let mut cpu = Computer::new(vec![3, 0, 4, 0, 99]);
+        assert_eq!(cpu.run(), Status::AwaitingInput);
+        cpu.push_input(42);
+        assert_eq!(cpu.run(), Status::Halted);
+        assert_eq!(cpu.output, vec![42]);
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+
+use crate::memory::Memory;
+
+/// Opt-in detection of guaranteed infinite loops, enabled with
+/// `Computer::detect_loops`.
+///
+/// At every backward jump the machine's state (ip, relative base, pending
+/// input and memory) is hashed. When a hash comes round a second time the
+/// state is kept, and if the machine reaches that exact state again it can
+/// never get out, so `try_run` fails with `Error::InfiniteLoop` and the
+/// range of addresses executed in between. Costs a pass over memory per
+/// backward jump and a hash per distinct state, so leave it off for long
+/// runs that are known to finish. Pushing input starts the detector
+/// afresh, since the machine can then go somewhere new.
+#[derive(Debug, Clone, Default)]
+pub struct LoopDetector {
+    seen: HashSet<u64>,
+    candidate: Option<Candidate>,
+}
+
+#[derive(Debug, Clone)]
+struct Candidate {
+    hash: u64,
+    ip: i64,
+    base: i64,
+    input: Vec<i64>,
+    memory: Memory,
+    // Addresses executed since the state was kept
+    start: i64,
+    end: i64,
+}
+
+impl LoopDetector {
+    pub fn new() -> LoopDetector {
+        LoopDetector::default()
+    }
+
+    /// Notes that the instruction at `ip` is about to run.
+    pub(crate) fn visit(&mut self, ip: i64) {
+        if let Some(candidate) = self.candidate.as_mut() {
+            candidate.start = candidate.start.min(ip);
+            candidate.end = candidate.end.max(ip);
+        }
+    }
+
+    /// Checks the state after a backward jump, returning the range of
+    /// addresses in the cycle if it's been here before.
+    pub(crate) fn check(
+        &mut self,
+        ip: i64,
+        base: i64,
+        input: &[i64],
+        memory: &Memory,
+    ) -> Option<(i64, i64)> {
+        let mut hasher = DefaultHasher::new();
+        (ip, base, input).hash(&mut hasher);
+        memory.iter().for_each(|v| v.hash(&mut hasher));
+        let hash = hasher.finish();
+
+        if let Some(c) = &self.candidate {
+            if c.hash == hash
+                && c.ip == ip
+                && c.base == base
+                && c.input == input
+                && c.memory == *memory
+            {
+                return Some((c.start, c.end));
+            }
+        }
+        let repeated = !self.seen.insert(hash);
+        if repeated && self.candidate.as_ref().is_none_or(|c| c.hash == hash) {
+            // Either the first repeat or a hash collision with the kept
+            // state: keep this one and wait for it to come round again.
+            self.candidate = Some(Candidate {
+                hash,
+                ip,
+                base,
+                input: input.to_vec(),
+                memory: memory.clone(),
+                start: ip,
+                end: ip,
+            });
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::asm::assemble;
+    use crate::{Computer, Error, Status};
+
+    #[test]
+    fn test_infinite_loop() {
+        let asm = assemble("toggle.asm", "loop: eq x, #0, x\njt #1, #loop\nx: data 0").unwrap();
+        let mut cpu = Computer::new(asm.program);
+        cpu.detect_loops();
+        let err = cpu.try_run().unwrap_err();
+        assert_eq!(
+            err,
+            Error::InfiniteLoop {
+                start: 0,
+                end: 4,
+                ip: 0
+            }
+        );
+        assert_eq!(err.to_string(), "Infinite loop over addresses 0..=4, ip=0");
+        cpu.debug = Some(asm.debug);
+        assert_eq!(
+            cpu.explain(&err),
+            "Infinite loop over addresses 0..=4, ip=0 at loop (toggle.asm:1)"
+        );
+    }
+
+    #[test]
+    fn test_finite_loops() {
+        // Counts down from the input, then waits for more
+        let mut cpu = Computer::new(vec![
+            3, 20, 4, 20, 1001, 20, -1, 20, 1005, 20, 2, 1105, 1, 0,
+        ]);
+        cpu.detect_loops();
+        cpu.push_input(3);
+        assert_eq!(cpu.try_run(), Ok(Status::AwaitingInput));
+        assert_eq!(cpu.output, vec![3, 2, 1]);
+        // Coming back to the same state doesn't count once there's new input
+        for _ in 0..3 {
+            cpu.push_input(1);
+            cpu.push_input(2);
+            assert_eq!(cpu.try_run(), Ok(Status::AwaitingInput));
+        }
+        assert_eq!(cpu.output, vec![3, 2, 1, 1, 2, 1, 1, 2, 1, 1, 2, 1]);
+    }
+}
@@ -0,0 +1,581 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::compiled::Compiled;
+use crate::decoded::Decoded;
+use crate::memory::Memory;
+use crate::stats::Stats;
+use crate::{Computer, Error, Status};
+
+/// An Intcode engine. Every implementation runs the same programs to the same
+/// results; they differ only in how they get there.
+pub trait Machine {
+    /// Replaces the program and resets the machine.
+    fn load(&mut self, program: &[i64]);
+    /// Restores the loaded program and clears both queues.
+    fn reset(&mut self);
+    fn push_input(&mut self, value: i64);
+    /// Runs until the program halts or needs input that hasn't been pushed.
+    fn run(&mut self) -> Result<Status, Error>;
+    fn take_output(&mut self) -> Vec<i64>;
+    fn status(&self) -> Status;
+    /// Reads a memory cell; cells past the end of memory read as zero.
+    fn read(&self, address: usize) -> i64;
+    fn write(&mut self, address: usize, value: i64);
+    /// An independent copy of the machine in its current state, sharing
+    /// memory with it copy-on-write.
+    fn fork(&self) -> Box<dyn Machine + Send + Sync>;
+    /// Starts collecting statistics for `stats` to report.
+    fn collect_stats(&mut self);
+    fn stats(&self) -> Option<&Stats>;
+}
+
+impl Machine for Computer {
+    fn load(&mut self, program: &[i64]) {
+        *self = Computer {
+            checked: self.checked,
+            stats: self.stats.as_ref().map(|_| Stats::default()),
+            ..Computer::new(program.to_vec())
+        };
+    }
+
+    fn reset(&mut self) {
+        Computer::reset(self);
+    }
+
+    fn push_input(&mut self, value: i64) {
+        Computer::push_input(self, value);
+    }
+
+    fn run(&mut self) -> Result<Status, Error> {
+        self.try_run()
+    }
+
+    fn take_output(&mut self) -> Vec<i64> {
+        self.output.drain(..).collect()
+    }
+
+    fn status(&self) -> Status {
+        let instruction = self.memory.get(self.ip as usize).unwrap_or(0);
+        if self.halted {
+            Status::Halted
+        } else if instruction % 100 == 3 && self.input.is_empty() {
+            Status::AwaitingInput
+        } else {
+            Status::Ready
+        }
+    }
+
+    fn read(&self, address: usize) -> i64 {
+        self.memory.get(address).unwrap_or(0)
+    }
+
+    fn write(&mut self, address: usize, value: i64) {
+        if address >= self.memory.len() {
+            self.memory.resize(address + 1);
+        }
+        self.memory[address] = value;
+    }
+
+    fn fork(&self) -> Box<dyn Machine + Send + Sync> {
+        Box::new(Computer::fork(self))
+    }
+
+    fn collect_stats(&mut self) {
+        self.stats = Some(Stats::default());
+    }
+
+    fn stats(&self) -> Option<&Stats> {
+        self.stats.as_ref()
+    }
+}
+
+/// The engines a day binary can be asked to run on with `--engine`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Engine {
+    /// `Computer`, decoding every instruction as it goes.
+    #[default]
+    Interpreter,
+    /// Caches each instruction's opcode and modes by address.
+    Decoded,
+    /// Caches whole instructions with their operands resolved.
+    Compiled,
+    /// `Computer` with overflow reported as an error.
+    Checked,
+}
+
+impl Engine {
+    pub const ALL: [Engine; 4] = [
+        Engine::Interpreter,
+        Engine::Decoded,
+        Engine::Compiled,
+        Engine::Checked,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Engine::Interpreter => "interpreter",
+            Engine::Decoded => "decoded",
+            Engine::Compiled => "compiled",
+            Engine::Checked => "checked",
+        }
+    }
+
+    pub fn machine(self, program: &[i64]) -> Box<dyn Machine + Send + Sync> {
+        match self {
+            Engine::Interpreter => Box::new(Computer::new(program.to_vec())),
+            Engine::Decoded => Box::new(Decoded::new(program)),
+            Engine::Compiled => Box::new(Compiled::new(program)),
+            Engine::Checked => {
+                let mut cpu = Computer::new(program.to_vec());
+                cpu.checked = true;
+                Box::new(cpu)
+            }
+        }
+    }
+
+    /// Picks the engine out of a day binary's arguments, accepting either
+    /// `--engine NAME` or `--engine=NAME`.
+    pub fn from_args(args: &[String]) -> Result<Engine, String> {
+        let mut engine = Engine::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if arg == "--engine" {
+                let name = args.next().ok_or("--engine needs a name")?;
+                engine = name.parse()?;
+            } else if let Some(name) = arg.strip_prefix("--engine=") {
+                engine = name.parse()?;
+            }
+        }
+        Ok(engine)
+    }
+}
+
+impl fmt::Display for Engine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Engine {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Engine, String> {
+        Engine::ALL
+            .iter()
+            .find(|e| e.name() == s)
+            .cloned()
+            .ok_or_else(|| {
+                let names: Vec<&str> = Engine::ALL.iter().map(|e| e.name()).collect();
+                format!("Unknown engine: {} (expected {})", s, names.join(", "))
+            })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Operand {
+    Immediate(i64),
+    Position(i64),
+    Relative(i64),
+}
+
+/// A decoded instruction. Targets are always `Position` or `Relative`; an
+/// immediate-mode target is the parameter cell itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Op {
+    Add(Operand, Operand, Operand),
+    Multiply(Operand, Operand, Operand),
+    Input(Operand),
+    Output(Operand),
+    JumpIfTrue(Operand, Operand),
+    JumpIfFalse(Operand, Operand),
+    LessThan(Operand, Operand, Operand),
+    Equals(Operand, Operand, Operand),
+    AdjustBase(Operand),
+    Halt,
+}
+
+impl Op {
+    fn opcode(self) -> i64 {
+        match self {
+            Op::Add(..) => 1,
+            Op::Multiply(..) => 2,
+            Op::Input(_) => 3,
+            Op::Output(_) => 4,
+            Op::JumpIfTrue(..) => 5,
+            Op::JumpIfFalse(..) => 6,
+            Op::LessThan(..) => 7,
+            Op::Equals(..) => 8,
+            Op::AdjustBase(_) => 9,
+            Op::Halt => 99,
+        }
+    }
+
+    fn len(self) -> i64 {
+        match self {
+            Op::Add(..) | Op::Multiply(..) | Op::LessThan(..) | Op::Equals(..) => 4,
+            Op::JumpIfTrue(..) | Op::JumpIfFalse(..) => 3,
+            Op::Input(_) | Op::Output(_) | Op::AdjustBase(_) => 2,
+            Op::Halt => 1,
+        }
+    }
+}
+
+/// Memory, registers and queues shared by the caching engines. Memory grows
+/// on demand, as `Computer`'s does.
+#[derive(Clone)]
+pub(crate) struct Core {
+    program: Memory,
+    pub memory: Memory,
+    pub ip: i64,
+    pub base: i64,
+    pub input: VecDeque<i64>,
+    pub output: Vec<i64>,
+    pub halted: bool,
+    // Address of the last cell written, for cache invalidation
+    pub written: Option<usize>,
+    pub stats: Option<Stats>,
+}
+
+impl Core {
+    pub fn new(program: &[i64]) -> Core {
+        Core {
+            program: Memory::from(program),
+            memory: Memory::from(program),
+            ip: 0,
+            base: 0,
+            input: VecDeque::new(),
+            output: Vec::new(),
+            halted: false,
+            written: None,
+            stats: None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        let program = self.program.clone();
+        *self = Core {
+            memory: program.clone(),
+            program,
+            stats: self.stats.as_ref().map(|_| Stats::default()),
+            ..Core::new(&[])
+        };
+    }
+
+    pub fn status(&self) -> Status {
+        if self.halted {
+            Status::Halted
+        } else if self.read(self.ip as usize) % 100 == 3 && self.input.is_empty() {
+            Status::AwaitingInput
+        } else {
+            Status::Ready
+        }
+    }
+
+    pub fn read(&self, address: usize) -> i64 {
+        self.memory.get(address).unwrap_or(0)
+    }
+
+    pub fn write(&mut self, address: usize, value: i64) {
+        if address >= self.memory.len() {
+            self.memory.resize(address + 1);
+        }
+        self.memory[address] = value;
+        self.written = Some(address);
+    }
+
+    fn get(&self, address: i64) -> Result<i64, Error> {
+        if address < 0 {
+            return Err(Error::InvalidAddress {
+                address,
+                ip: self.ip,
+            });
+        }
+        Ok(self.read(address as usize))
+    }
+
+    fn set(&mut self, target: Operand, value: i64) -> Result<(), Error> {
+        let address = match target {
+            Operand::Position(a) => a,
+            Operand::Relative(offset) => self.base + offset,
+            Operand::Immediate(_) => unreachable!("immediate target"),
+        };
+        if address < 0 {
+            return Err(Error::InvalidAddress {
+                address,
+                ip: self.ip,
+            });
+        }
+        self.write(address as usize, value);
+        self.touched(address);
+        Ok(())
+    }
+
+    fn value(&mut self, operand: Operand) -> Result<i64, Error> {
+        let address = match operand {
+            Operand::Immediate(v) => return Ok(v),
+            Operand::Position(a) => a,
+            Operand::Relative(offset) => self.base + offset,
+        };
+        let value = self.get(address)?;
+        self.touched(address);
+        Ok(value)
+    }
+
+    fn touched(&mut self, address: i64) {
+        if let Some(stats) = self.stats.as_mut() {
+            stats.touched(address as usize);
+        }
+    }
+
+    /// The opcode and parameter modes of the instruction at `ip`.
+    pub fn opcode(&self, ip: i64) -> Result<(i32, [u8; 3]), Error> {
+        Ok(Computer::decode(self.get(ip)? as i32))
+    }
+
+    /// Builds the instruction at `ip` from its opcode and modes, reading the
+    /// parameter cells from memory.
+    pub fn op(&self, ip: i64, opcode: i32, modes: [u8; 3]) -> Result<Op, Error> {
+        let param = |i: usize| -> Result<Operand, Error> {
+            let cell = ip + 1 + i as i64;
+            let value = self.get(cell)?;
+            match modes[i] {
+                0 => Ok(Operand::Position(value)),
+                1 => Ok(Operand::Immediate(value)),
+                2 => Ok(Operand::Relative(value)),
+                mode => Err(Error::UnknownMode { mode, ip }),
+            }
+        };
+        let target = |i: usize| -> Result<Operand, Error> {
+            match param(i)? {
+                Operand::Immediate(_) => Ok(Operand::Position(ip + 1 + i as i64)),
+                operand => Ok(operand),
+            }
+        };
+        Ok(match opcode {
+            1 => Op::Add(param(0)?, param(1)?, target(2)?),
+            2 => Op::Multiply(param(0)?, param(1)?, target(2)?),
+            3 => Op::Input(target(0)?),
+            4 => Op::Output(param(0)?),
+            5 => Op::JumpIfTrue(param(0)?, param(1)?),
+            6 => Op::JumpIfFalse(param(0)?, param(1)?),
+            7 => Op::LessThan(param(0)?, param(1)?, target(2)?),
+            8 => Op::Equals(param(0)?, param(1)?, target(2)?),
+            9 => Op::AdjustBase(param(0)?),
+            99 => Op::Halt,
+            opcode => {
+                return Err(Error::UnknownOpcode {
+                    opcode: opcode as i64,
+                    ip,
+                })
+            }
+        })
+    }
+
+    /// Executes `op` as the instruction at `ip`, returning a status if the
+    /// machine can't go any further.
+    pub fn execute(&mut self, op: Op) -> Result<Option<Status>, Error> {
+        let mut next = self.ip + op.len();
+        match op {
+            Op::Add(x, y, z) => {
+                let (x, y) = (self.value(x)?, self.value(y)?);
+                self.set(z, x + y)?;
+            }
+            Op::Multiply(x, y, z) => {
+                let (x, y) = (self.value(x)?, self.value(y)?);
+                self.set(z, x * y)?;
+            }
+            Op::Input(target) => match self.input.pop_front() {
+                Some(value) => {
+                    self.set(target, value)?;
+                    if let Some(stats) = self.stats.as_mut() {
+                        stats.inputs += 1;
+                    }
+                }
+                None => {
+                    self.touched(self.ip);
+                    return Ok(Some(Status::AwaitingInput));
+                }
+            },
+            Op::Output(x) => {
+                let value = self.value(x)?;
+                self.output.push(value);
+                if let Some(stats) = self.stats.as_mut() {
+                    stats.outputs += 1;
+                }
+            }
+            Op::JumpIfTrue(x, y) => {
+                if self.value(x)? != 0 {
+                    next = self.value(y)?;
+                }
+            }
+            Op::JumpIfFalse(x, y) => {
+                if self.value(x)? == 0 {
+                    next = self.value(y)?;
+                }
+            }
+            Op::LessThan(x, y, z) => {
+                let value = (self.value(x)? < self.value(y)?) as i64;
+                self.set(z, value)?;
+            }
+            Op::Equals(x, y, z) => {
+                let value = (self.value(x)? == self.value(y)?) as i64;
+                self.set(z, value)?;
+            }
+            Op::AdjustBase(x) => {
+                self.base += self.value(x)?;
+                if let Some(stats) = self.stats.as_mut() {
+                    stats.max_base = stats.max_base.max(self.base);
+                }
+            }
+            Op::Halt => self.halted = true,
+        }
+        if let Some(stats) = self.stats.as_mut() {
+            stats.executed(op.opcode());
+            stats.touched((self.ip + op.len() - 1) as usize);
+        }
+        self.ip = next;
+        Ok(if self.halted {
+            Some(Status::Halted)
+        } else {
+            None
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use conformance::Vm;
+
+    // One wrapper type per engine, so the suite can construct them by type
+    struct Engined<const E: usize> {
+        machine: Box<dyn Machine + Send + Sync>,
+        output: Vec<i64>,
+    }
+
+    impl<const E: usize> Vm for Engined<E> {
+        fn load(program: Vec<i64>) -> Self {
+            Engined {
+                machine: Engine::ALL[E].machine(&program),
+                output: Vec::new(),
+            }
+        }
+
+        fn push_input(&mut self, value: i64) {
+            self.machine.push_input(value);
+        }
+
+        fn run(&mut self) {
+            self.machine.run().unwrap();
+            self.output.extend(self.machine.take_output());
+        }
+
+        fn output(&self) -> Vec<i64> {
+            self.output.clone()
+        }
+
+        fn read(&self, address: usize) -> i64 {
+            self.machine.read(address)
+        }
+
+        fn write(&mut self, address: usize, value: i64) {
+            self.machine.write(address, value);
+        }
+    }
+
+    #[test]
+    fn test_conformance() {
+        let mut failures = conformance::run_all::<Engined<0>>();
+        failures.extend(conformance::run_all::<Engined<1>>());
+        failures.extend(conformance::run_all::<Engined<2>>());
+        failures.extend(conformance::run_all::<Engined<3>>());
+        assert!(failures.is_empty(), "{:#?}", failures);
+    }
+
+    #[test]
+    fn test_engine_names() {
+        for engine in Engine::ALL.iter() {
+            assert_eq!(engine.name().parse(), Ok(*engine));
+        }
+        assert!("jit".parse::<Engine>().is_err());
+        let args = |s: &str| s.split_whitespace().map(String::from).collect::<Vec<_>>();
+        assert_eq!(Engine::from_args(&args("x")), Ok(Engine::Interpreter));
+        assert_eq!(
+            Engine::from_args(&args("--engine compiled")),
+            Ok(Engine::Compiled)
+        );
+        assert_eq!(
+            Engine::from_args(&args("--engine=checked")),
+            Ok(Engine::Checked)
+        );
+        assert!(Engine::from_args(&args("--engine")).is_err());
+    }
+
+    #[test]
+    fn test_status_and_reset() {
+        for engine in Engine::ALL.iter() {
+            let mut m = engine.machine(&[3, 0, 4, 0, 99]);
+            assert_eq!(m.status(), Status::AwaitingInput, "{}", engine);
+            assert_eq!(m.run(), Ok(Status::AwaitingInput));
+            m.push_input(42);
+            assert_eq!(m.status(), Status::Ready);
+            assert_eq!(m.run(), Ok(Status::Halted));
+            assert_eq!(m.take_output(), vec![42]);
+            assert_eq!(m.take_output(), vec![]);
+            assert_eq!(m.read(0), 42);
+            m.reset();
+            assert_eq!(m.read(0), 3);
+            m.load(&[104, 7, 99]);
+            assert_eq!(m.run(), Ok(Status::Halted));
+            assert_eq!(m.take_output(), vec![7]);
+        }
+    }
+
+    #[test]
+    fn test_fork() {
+        for engine in Engine::ALL.iter() {
+            let mut m = engine.machine(&[3, 9, 4, 9, 1105, 1, 0, 99, 0, 0]);
+            m.push_input(1);
+            assert_eq!(m.run(), Ok(Status::AwaitingInput));
+            let mut child = m.fork();
+            child.push_input(2);
+            m.push_input(3);
+            assert_eq!(child.run(), Ok(Status::AwaitingInput));
+            assert_eq!(m.run(), Ok(Status::AwaitingInput));
+            assert_eq!(child.take_output(), vec![1, 2], "{}", engine);
+            assert_eq!(m.take_output(), vec![1, 3], "{}", engine);
+        }
+    }
+
+    #[test]
+    fn test_self_modifying() {
+        // Outputs its own parameter, rewrites it to 9 and jumps back once
+        let program = [
+            104, 0, 1101, 9, 0, 1, 1005, 17, 16, 1101, 1, 0, 17, 1105, 1, 0, 99, 0,
+        ];
+        for engine in Engine::ALL.iter() {
+            let mut m = engine.machine(&program);
+            assert_eq!(m.run(), Ok(Status::Halted), "{}", engine);
+            assert_eq!(m.take_output(), vec![0, 9], "{}", engine);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        for engine in Engine::ALL.iter() {
+            let mut m = engine.machine(&[1, 0, 0, 0, 42]);
+            assert_eq!(
+                m.run(),
+                Err(Error::UnknownOpcode { opcode: 42, ip: 4 }),
+                "{}",
+                engine
+            );
+            let mut m = engine.machine(&[4, -1, 99]);
+            assert_eq!(m.run(), Err(Error::InvalidAddress { address: -1, ip: 0 }));
+        }
+        let mut m = Engine::Checked.machine(&[1102, i64::MAX, 2, 0, 99]);
+        assert_eq!(m.run(), Err(Error::Overflow { ip: 0 }));
+    }
+}
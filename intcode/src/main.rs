@@ -0,0 +1,249 @@
+use std::env;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::process;
+
+use std::path::Path;
+use std::time::Instant;
+
+use intcode::asm::{assemble_file, Assembly};
+use intcode::debug::DebugInfo;
+use intcode::diff::{diff, Change};
+use intcode::export::Cfg;
+use intcode::lang::compile_file;
+use intcode::machine::Engine;
+use intcode::options::Options;
+use intcode::patch::{Patch, Patcher, Strategy};
+use intcode::read_program;
+use intcode::session::Session;
+use intcode::tui::Dashboard;
+use intcode::watch::Watch;
+use intcode::Computer;
+
+const USAGE: &str = "\
+usage: intcode repl [program]
+       intcode asm <source> <program>
+       intcode compile <source> <program>
+       intcode replay <program> <session>
+       intcode watch <program> <dir> [steps-per-frame [input...]]
+       intcode bench <program> [input...]
+       intcode diff <program> <program>
+       intcode tui <program> [input...]
+       intcode export <program> <output.rs|output.wat>
+       intcode patch <program> <target>=<goal> <address>=<min>-<max>... [--monotonic]";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let result = match args[..] {
+        ["repl"] => intcode::repl::run(None).map_err(|e| e.to_string()),
+        ["repl", program] => intcode::repl::run(Some(program)).map_err(|e| e.to_string()),
+        ["asm", source, program] => assemble_file(source).and_then(|a| write(&a, program)),
+        ["compile", source, program] => compile_file(source).and_then(|a| write(&a, program)),
+        ["replay", program, session] => replay(program, session),
+        ["watch", program, dir, ref rest @ ..] => watch(program, dir, rest),
+        ["bench", program, ref inputs @ ..] => bench(program, inputs),
+        ["diff", left, right] => compare(left, right),
+        ["tui", program, ref inputs @ ..] => tui(program, inputs),
+        ["export", program, output] => export(program, output),
+        ["patch", program, goal, ref rest @ ..] => patch(program, goal, rest),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
+
+// Writes an assembled program and its debug info sidecar
+fn write(asm: &Assembly, program: &str) -> Result<(), String> {
+    let text: Vec<String> = asm.program.iter().map(i64::to_string).collect();
+    fs::write(program, text.join(",") + "\n").map_err(|e| format!("{}: {}", program, e))?;
+    let sidecar = DebugInfo::sidecar(program);
+    asm.debug
+        .save(&sidecar)
+        .map_err(|e| format!("{}: {}", sidecar, e))?;
+    println!("{} cells written to {}", asm.program.len(), program);
+    Ok(())
+}
+
+fn replay(program: &str, session: &str) -> Result<(), String> {
+    let program = read_program(program)?;
+    let session = Session::load(session)?;
+    session.replay(&program).map_err(|e| e.to_string())?;
+    println!("ok: {} events replayed", session.events.len());
+    Ok(())
+}
+
+fn compare(left: &str, right: &str) -> Result<(), String> {
+    let changes = diff(&read_program(left)?, &read_program(right)?);
+    for change in &changes {
+        println!("{}", change);
+    }
+    let count = |f: fn(&Change) -> bool| changes.iter().filter(|c| f(c)).count();
+    println!(
+        "{} constants, {} instructions, {} blocks changed",
+        count(|c| matches!(c, Change::Constant { .. })),
+        count(|c| matches!(c, Change::Instruction { .. })),
+        count(|c| matches!(c, Change::Deleted { .. } | Change::Inserted { .. }))
+    );
+    Ok(())
+}
+
+fn parse_numbers(args: &[&str]) -> Result<Vec<i64>, String> {
+    args.iter()
+        .map(|a| a.parse::<i64>().map_err(|e| format!("{}: {}", a, e)))
+        .collect()
+}
+
+fn watch(program: &str, dir: &str, args: &[&str]) -> Result<(), String> {
+    let (frame_steps, inputs) = match args.split_first() {
+        Some((steps, inputs)) => (
+            steps
+                .parse::<u64>()
+                .map_err(|e| format!("{}: {}", steps, e))?,
+            parse_numbers(inputs)?,
+        ),
+        None => (1000, Vec::new()),
+    };
+
+    let mut cpu = Computer::new(read_program(program)?);
+    cpu.watch = Some(Watch::new(frame_steps));
+    inputs.iter().for_each(|v| cpu.push_input(*v));
+    let status = cpu.try_run().map_err(|e| cpu.explain(&e))?;
+
+    let watch = cpu.watch.unwrap();
+    watch
+        .export(Path::new(dir), 64, 4)
+        .map_err(|e| format!("{}: {}", dir, e))?;
+    print!("{}", watch.legend());
+    println!(
+        "{:?} after {} steps, {} frames written to {}",
+        status,
+        cpu.steps,
+        watch.frames().len(),
+        dir
+    );
+    Ok(())
+}
+
+fn export(program: &str, output: &str) -> Result<(), String> {
+    let cfg = Cfg::build(&read_program(program)?).map_err(|e| format!("{}: {}", program, e))?;
+    let text = if output.ends_with(".wat") {
+        cfg.to_wat(program)
+    } else if output.ends_with(".rs") {
+        cfg.to_rust(program)
+    } else {
+        return Err(format!("{}: expected a .rs or .wat file", output));
+    };
+    fs::write(output, text).map_err(|e| format!("{}: {}", output, e))?;
+    println!("{} blocks written to {}", cfg.blocks.len(), output);
+    Ok(())
+}
+
+// Splits `address=value`
+fn assignment(arg: &str) -> Result<(usize, &str), String> {
+    let (address, value) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("{}: expected address=value", arg))?;
+    let address = address.parse().map_err(|e| format!("{}: {}", arg, e))?;
+    Ok((address, value))
+}
+
+// Splits `min-max`, or takes a single value, either of which may be negative
+fn values(range: &str) -> Result<RangeInclusive<i64>, String> {
+    if range.is_empty() {
+        return Err("expected a value or min-max".to_string());
+    }
+    let bounds = match range.char_indices().skip(1).find(|(_, c)| *c == '-') {
+        Some((i, _)) => parse_numbers(&[&range[..i], &range[i + 1..]])?,
+        None => parse_numbers(&[range, range])?,
+    };
+    if bounds[0] > bounds[1] {
+        return Err("empty range".to_string());
+    }
+    Ok(bounds[0]..=bounds[1])
+}
+
+fn patch(program: &str, goal: &str, args: &[&str]) -> Result<(), String> {
+    let (target, goal) = assignment(goal)?;
+    let goal = parse_numbers(&[goal])?[0];
+    let mut strategy = Strategy::Exhaustive;
+    let mut patches = Vec::new();
+    for arg in args {
+        if *arg == "--monotonic" {
+            strategy = Strategy::Monotonic;
+            continue;
+        }
+        let (address, range) = assignment(arg)?;
+        let values = values(range).map_err(|e| format!("{}: {}", arg, e))?;
+        patches.push(Patch::new(address, values));
+    }
+
+    let program = read_program(program)?;
+    let mut addresses = patches.iter().map(|p| p.address).chain(Some(target));
+    if let Some(address) = addresses.find(|a| *a >= program.len()) {
+        return Err(format!(
+            "{}: past the end of the program ({} cells)",
+            address,
+            program.len()
+        ));
+    }
+    let options = Options::default();
+    let patcher = Patcher::new(&program, patches.clone(), target, goal, &options);
+    let solutions = patcher.solve(strategy).map_err(|e| e.to_string())?;
+    for solution in &solutions {
+        let cells: Vec<String> = patches
+            .iter()
+            .zip(solution)
+            .map(|(p, v)| format!("{}={}", p.address, v))
+            .collect();
+        println!("{}", cells.join(" "));
+    }
+    println!("{} solutions", solutions.len());
+    Ok(())
+}
+
+fn tui(program: &str, args: &[&str]) -> Result<(), String> {
+    let mut cpu = Computer::new(read_program(program)?);
+    parse_numbers(args)?
+        .into_iter()
+        .for_each(|v| cpu.push_input(v));
+    let mut dashboard = Dashboard::new();
+    dashboard.add(program, cpu);
+    dashboard.run().map_err(|e| e.to_string())
+}
+
+// Runs the program on every engine with the same inputs, checking that they
+// agree on the output.
+fn bench(program: &str, args: &[&str]) -> Result<(), String> {
+    let program = read_program(program)?;
+    let inputs = parse_numbers(args)?;
+    let mut expected: Option<Vec<i64>> = None;
+    for engine in Engine::ALL.iter() {
+        let start = Instant::now();
+        let mut machine = engine.machine(&program);
+        inputs.iter().for_each(|v| machine.push_input(*v));
+        let status = machine.run().map_err(|e| format!("{}: {}", engine, e))?;
+        let elapsed = start.elapsed();
+        let output = machine.take_output();
+        println!(
+            "{:<12} {:>10.3} ms  {:?}, {} outputs",
+            engine.name(),
+            elapsed.as_secs_f64() * 1000.0,
+            status,
+            output.len()
+        );
+        match &expected {
+            Some(expected) if *expected != output => {
+                return Err(format!("{} disagrees on the output", engine));
+            }
+            Some(_) => (),
+            None => expected = Some(output),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,184 @@
+use std::fmt;
+use std::ops::{Index, IndexMut};
+use std::sync::Arc;
+
+pub const PAGE_SIZE: usize = 512;
+
+type Page = [i64; PAGE_SIZE];
+
+/// Intcode memory split into fixed-size pages that are shared between clones
+/// until one of them writes to a page, so copying a machine only costs the
+/// pages that later diverge.
+#[derive(Clone, Default)]
+pub struct Memory {
+    pages: Vec<Arc<Page>>,
+    len: usize,
+}
+
+impl Memory {
+    pub fn new() -> Memory {
+        Memory::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, address: usize) -> Option<i64> {
+        if address < self.len {
+            Some(self.pages[address / PAGE_SIZE][address % PAGE_SIZE])
+        } else {
+            None
+        }
+    }
+
+    /// Grows memory with zeroes or truncates it to `len` cells. New pages all
+    /// share one zeroed page until they're written.
+    pub fn resize(&mut self, len: usize) {
+        if len < self.len {
+            for address in len..self.len.min(len.div_ceil(PAGE_SIZE) * PAGE_SIZE) {
+                self[address] = 0;
+            }
+        }
+        let pages = len.div_ceil(PAGE_SIZE);
+        if pages > self.pages.len() {
+            let zero = Arc::new([0; PAGE_SIZE]);
+            self.pages.resize(pages, zero);
+        } else {
+            self.pages.truncate(pages);
+        }
+        self.len = len;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = i64> + '_ {
+        self.pages
+            .iter()
+            .flat_map(|p| p.iter().cloned())
+            .take(self.len)
+    }
+
+    pub fn to_vec(&self) -> Vec<i64> {
+        self.iter().collect()
+    }
+
+    pub fn pages(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// How many pages are still shared with `other`.
+    pub fn shared_pages(&self, other: &Memory) -> usize {
+        self.pages
+            .iter()
+            .zip(&other.pages)
+            .filter(|(a, b)| Arc::ptr_eq(a, b))
+            .count()
+    }
+}
+
+impl From<&[i64]> for Memory {
+    fn from(cells: &[i64]) -> Memory {
+        let pages = cells
+            .chunks(PAGE_SIZE)
+            .map(|chunk| {
+                let mut page = [0; PAGE_SIZE];
+                page[..chunk.len()].copy_from_slice(chunk);
+                Arc::new(page)
+            })
+            .collect();
+        Memory {
+            pages,
+            len: cells.len(),
+        }
+    }
+}
+
+impl From<Vec<i64>> for Memory {
+    fn from(cells: Vec<i64>) -> Memory {
+        Memory::from(&cells[..])
+    }
+}
+
+impl Index<usize> for Memory {
+    type Output = i64;
+
+    fn index(&self, address: usize) -> &i64 {
+        assert!(
+            address < self.len,
+            "address {} out of range for memory of {} cells",
+            address,
+            self.len
+        );
+        &self.pages[address / PAGE_SIZE][address % PAGE_SIZE]
+    }
+}
+
+impl IndexMut<usize> for Memory {
+    fn index_mut(&mut self, address: usize) -> &mut i64 {
+        assert!(
+            address < self.len,
+            "address {} out of range for memory of {} cells",
+            address,
+            self.len
+        );
+        &mut Arc::make_mut(&mut self.pages[address / PAGE_SIZE])[address % PAGE_SIZE]
+    }
+}
+
+impl PartialEq for Memory {
+    fn eq(&self, other: &Memory) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl Eq for Memory {}
+
+impl fmt::Debug for Memory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Memory")
+            .field("len", &self.len)
+            .field("pages", &self.pages.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_copy_on_write() {
+        let mut a = Memory::from(vec![1, 2, 3]);
+        a.resize(PAGE_SIZE * 3);
+        let mut b = a.clone();
+        assert_eq!(b.shared_pages(&a), 3);
+        b[PAGE_SIZE + 1] = 7;
+        assert_eq!(b.shared_pages(&a), 2);
+        assert_eq!(a[PAGE_SIZE + 1], 0);
+        assert_eq!(b[PAGE_SIZE + 1], 7);
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_resize() {
+        let mut m = Memory::from(vec![1, 2, 3, 4]);
+        m.resize(2);
+        assert_eq!(m.to_vec(), vec![1, 2]);
+        assert_eq!(m.get(2), None);
+        m.resize(4);
+        assert_eq!(m.to_vec(), vec![1, 2, 0, 0]);
+        m.resize(PAGE_SIZE + 1);
+        assert_eq!(m.pages(), 2);
+        assert_eq!(m.iter().count(), PAGE_SIZE + 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn test_out_of_range() {
+        let m = Memory::from(vec![1]);
+        let _ = m[1];
+    }
+}
@@ -0,0 +1,101 @@
+use std::sync::Mutex;
+
+use crate::machine::{Engine, Machine};
+use crate::stats::Stats;
+
+/// Command line settings shared by the Intcode day binaries: `--engine NAME`
+/// picks the engine and `--stats` reports what the machines did, as JSON on
+/// stderr so the answers on stdout are unchanged.
+#[derive(Debug, Default)]
+pub struct Options {
+    pub engine: Engine,
+    pub stats: bool,
+    current: Mutex<Stats>,
+    parts: Mutex<Vec<(String, Stats)>>,
+}
+
+impl Options {
+    pub fn new(engine: Engine) -> Options {
+        Options {
+            engine,
+            ..Options::default()
+        }
+    }
+
+    pub fn from_args(args: &[String]) -> Result<Options, String> {
+        Ok(Options {
+            engine: Engine::from_args(args)?,
+            stats: args.iter().any(|a| a == "--stats"),
+            ..Options::default()
+        })
+    }
+
+    /// A machine on the selected engine, collecting statistics if asked to.
+    pub fn machine(&self, program: &[i64]) -> Box<dyn Machine + Send + Sync> {
+        let mut machine = self.engine.machine(program);
+        if self.stats {
+            machine.collect_stats();
+        }
+        machine
+    }
+
+    /// Adds a machine's statistics to the current part's.
+    pub fn collect(&self, machine: &dyn Machine) {
+        if let Some(stats) = machine.stats() {
+            self.current.lock().unwrap().merge(stats);
+        }
+    }
+
+    /// Closes off the statistics for a part of the puzzle.
+    pub fn finish(&self, part: &str) {
+        let stats = std::mem::take(&mut *self.current.lock().unwrap());
+        self.parts.lock().unwrap().push((part.to_string(), stats));
+    }
+
+    /// The statistics for every finished part as a JSON object.
+    pub fn report(&self) -> String {
+        let parts: Vec<String> = self
+            .parts
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(part, stats)| format!("\"{}\": {}", part, stats.to_json()))
+            .collect();
+        format!("{{{}}}", parts.join(", "))
+    }
+
+    /// Prints the report to stderr if `--stats` was given.
+    pub fn print_report(&self) {
+        if self.stats {
+            eprintln!("{}", self.report());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let args: Vec<String> = vec!["--stats".to_string(), "--engine=compiled".to_string()];
+        let options = Options::from_args(&args).unwrap();
+        assert_eq!(options.engine, Engine::Compiled);
+        for input in 1..=2 {
+            let mut machine = options.machine(&[3, 0, 4, 0, 99]);
+            machine.push_input(input);
+            machine.run().unwrap();
+            options.collect(machine.as_ref());
+        }
+        options.finish("part1");
+        options.finish("part2");
+        let report = options.report();
+        assert!(report.starts_with(
+            "{\"part1\": {\"instructions\": {\"input\": 2, \"output\": 2, \"halt\": 2}, "
+        ));
+        assert!(report.contains("\"part2\": {\"instructions\": {}, \"total_instructions\": 0"));
+
+        let options = Options::new(Engine::Interpreter);
+        assert!(options.machine(&[99]).stats().is_none());
+    }
+}
@@ -0,0 +1,252 @@
+use std::ops::RangeInclusive;
+use std::sync::Mutex;
+
+use crate::machine::Machine;
+use crate::options::Options;
+use crate::search::{default_threads, find_all, Space};
+use crate::{Error, Status};
+
+/// A cell to overwrite before the program runs, and the values to try in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Patch {
+    pub address: usize,
+    pub values: RangeInclusive<i64>,
+}
+
+impl Patch {
+    pub fn new(address: usize, values: RangeInclusive<i64>) -> Patch {
+        Patch { address, values }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// Runs every combination of values.
+    Exhaustive,
+    /// Assumes the target never decreases as the first patch's value goes
+    /// up, as with day02's noun, and binary searches it for each combination
+    /// of the others. Falls back to a scan wherever a run fails.
+    Monotonic,
+}
+
+/// Searches for the patches that leave `goal` at the `target` address once
+/// a program halts: day02's noun and verb, in general form. Runs on the
+/// engine `options` selects, adding every run to its statistics.
+pub struct Patcher<'a> {
+    template: Box<dyn Machine + Send + Sync>,
+    patches: Vec<Patch>,
+    target: usize,
+    goal: i64,
+    options: &'a Options,
+    pub threads: usize,
+}
+
+impl<'a> Patcher<'a> {
+    pub fn new(
+        program: &[i64],
+        patches: Vec<Patch>,
+        target: usize,
+        goal: i64,
+        options: &'a Options,
+    ) -> Patcher<'a> {
+        Patcher {
+            template: options.machine(program),
+            patches,
+            target,
+            goal,
+            options,
+            threads: default_threads(),
+        }
+    }
+
+    /// The value at the target address after running with `values` patched
+    /// in, or `None` if the program stops for input instead of halting.
+    pub fn output(&self, values: &[i64]) -> Result<Option<i64>, Error> {
+        let mut machine = self.template.fork();
+        for (patch, value) in self.patches.iter().zip(values) {
+            machine.write(patch.address, *value);
+        }
+        let status = machine.run();
+        self.options.collect(machine.as_ref());
+        match status? {
+            Status::Halted => Ok(Some(machine.read(self.target))),
+            _ => Ok(None),
+        }
+    }
+
+    /// Every solution, as values in patch order, sorted. Runs that fail
+    /// don't match, but if nothing does, the error from the first of them
+    /// in patch order is returned instead.
+    pub fn solve(&self, strategy: Strategy) -> Result<Vec<Vec<i64>>, Error> {
+        let space = Space::Product(
+            self.patches
+                .iter()
+                .map(|p| p.values.clone().collect())
+                .collect(),
+        );
+        let errors = Mutex::new(Vec::new());
+        let output = |values: &[i64]| match self.output(values) {
+            Ok(output) => output,
+            Err(e) => {
+                errors.lock().unwrap().push((values.to_vec(), e));
+                None
+            }
+        };
+        let solutions = match strategy {
+            Strategy::Exhaustive => find_all(&space, self.threads, |values| {
+                output(values) == Some(self.goal)
+            }),
+            Strategy::Monotonic => self.monotonic(&output),
+        };
+        let first_error = errors
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .min_by(|a, b| a.0.cmp(&b.0));
+        match first_error {
+            Some((_, error)) if solutions.is_empty() => Err(error),
+            _ => Ok(solutions),
+        }
+    }
+
+    fn monotonic(&self, output: &dyn Fn(&[i64]) -> Option<i64>) -> Vec<Vec<i64>> {
+        let (first, rest) = match self.patches.split_first() {
+            Some(split) => split,
+            None => return Vec::new(),
+        };
+        let rest = Space::Product(rest.iter().map(|p| p.values.clone().collect()).collect());
+        let mut solutions = Vec::new();
+        for i in 0..rest.len() {
+            let rest = rest.get(i);
+            let output = |value: i64| {
+                let mut values = vec![value];
+                values.extend(&rest);
+                output(&values)
+            };
+            let found = match lower_bound(first.values.clone(), self.goal, output) {
+                Some(start) => (start..=*first.values.end())
+                    .take_while(|v| output(*v) == Some(self.goal))
+                    .collect(),
+                None => first
+                    .values
+                    .clone()
+                    .filter(|v| output(*v) == Some(self.goal))
+                    .collect::<Vec<i64>>(),
+            };
+            for value in found {
+                let mut solution = vec![value];
+                solution.extend(&rest);
+                solutions.push(solution);
+            }
+        }
+        solutions.sort();
+        solutions
+    }
+}
+
+// The first value in `range` whose output isn't below `goal`, or the last
+// value if none is, or `None` if a run fails on the way
+fn lower_bound<F>(range: RangeInclusive<i64>, goal: i64, output: F) -> Option<i64>
+where
+    F: Fn(i64) -> Option<i64>,
+{
+    let (mut lo, mut hi) = (*range.start(), *range.end());
+    while lo < hi {
+        // Rounds down, and can't overflow however far apart the ends are
+        let mid = (lo >> 1) + (hi >> 1) + (lo & hi & 1);
+        if output(mid)? < goal {
+            lo = mid.checked_add(1)?;
+        } else {
+            hi = mid;
+        }
+    }
+    Some(lo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+    use crate::machine::Engine;
+    use crate::parse_instructions;
+    use std::fs;
+
+    #[test]
+    fn test_day02() {
+        let input = fs::read_to_string("../day02/data/input.txt").expect("Error reading file");
+        let program = parse_instructions(&input).unwrap();
+        let patches = vec![Patch::new(1, 0..=99), Patch::new(2, 0..=99)];
+        for engine in Engine::ALL.iter() {
+            let options = Options::new(*engine);
+            let patcher = Patcher::new(&program, patches.clone(), 0, 19690720, &options);
+            assert_eq!(patcher.output(&[12, 2]), Ok(Some(4484226)), "{}", engine);
+            assert_eq!(patcher.solve(Strategy::Monotonic), Ok(vec![vec![56, 96]]));
+        }
+        let options = Options::default();
+        let patcher = Patcher::new(&program, patches, 0, 19690720, &options);
+        assert_eq!(patcher.solve(Strategy::Exhaustive), Ok(vec![vec![56, 96]]));
+    }
+
+    #[test]
+    fn test_all_solutions() {
+        // Adds the two immediates into cell 5
+        let program = assemble("t", "add #0, #0, 5\nhlt\ndata 0").unwrap().program;
+        let patches = vec![Patch::new(1, 0..=9), Patch::new(2, 0..=9)];
+        let options = Options::default();
+        let patcher = Patcher::new(&program, patches, 5, 5, &options);
+        let expected: Vec<Vec<i64>> = (0..=5).map(|n| vec![n, 5 - n]).collect();
+        assert_eq!(patcher.solve(Strategy::Exhaustive), Ok(expected.clone()));
+        assert_eq!(patcher.solve(Strategy::Monotonic), Ok(expected));
+    }
+
+    #[test]
+    fn test_lower_bound() {
+        let identity = |v: i64| Some(v);
+        assert_eq!(lower_bound(0..=99, 56, identity), Some(56));
+        assert_eq!(lower_bound(0..=99, 200, identity), Some(99));
+        assert_eq!(
+            lower_bound(i64::MAX - 5..=i64::MAX, i64::MAX, identity),
+            Some(i64::MAX)
+        );
+        assert_eq!(lower_bound(i64::MIN..=i64::MAX, -7, identity), Some(-7));
+        assert_eq!(
+            lower_bound(i64::MIN..=i64::MAX, i64::MIN, identity),
+            Some(i64::MIN)
+        );
+    }
+
+    #[test]
+    fn test_failures() {
+        // Patching the opcode: 1 and 2 halt, 3 waits for input and 4 runs
+        // into a bad opcode, so the binary search has to fall back to a scan
+        let program = vec![0, 5, 6, 0, 99, 10, 20];
+        let options = Options::default();
+        let patcher = Patcher::new(&program, vec![Patch::new(0, 1..=4)], 0, 200, &options);
+        assert_eq!(patcher.output(&[1]), Ok(Some(30)));
+        assert_eq!(patcher.output(&[3]), Ok(None));
+        assert_eq!(
+            patcher.output(&[4]),
+            Err(Error::UnknownOpcode { opcode: 10, ip: 5 })
+        );
+        assert_eq!(patcher.solve(Strategy::Exhaustive), Ok(vec![vec![2]]));
+        assert_eq!(patcher.solve(Strategy::Monotonic), Ok(vec![vec![2]]));
+
+        // With no solution the first failure is the answer
+        let patcher = Patcher::new(&program, vec![Patch::new(0, 1..=4)], 0, 7, &options);
+        assert_eq!(
+            patcher.solve(Strategy::Exhaustive),
+            Err(Error::UnknownOpcode { opcode: 10, ip: 5 })
+        );
+        let patcher = Patcher::new(&program, vec![Patch::new(0, 1..=3)], 0, 7, &options);
+        assert_eq!(patcher.solve(Strategy::Exhaustive), Ok(Vec::new()));
+        let program = vec![1, 0, 0, 0, 42];
+        let patches = vec![Patch::new(1, 0..=9), Patch::new(2, 0..=9)];
+        let patcher = Patcher::new(&program, patches, 0, 7, &options);
+        for strategy in [Strategy::Exhaustive, Strategy::Monotonic].iter() {
+            assert_eq!(
+                patcher.solve(*strategy),
+                Err(Error::UnknownOpcode { opcode: 42, ip: 4 })
+            );
+        }
+    }
+}
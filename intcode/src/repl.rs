@@ -0,0 +1,436 @@
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+use crate::asm::assemble_file;
+use crate::debug::DebugInfo;
+use crate::lang::compile_file;
+use crate::{read_program, Computer, Status};
+
+const HELP: &str = "\
+load <file>          load a program (or .asm/.ic source) and reset the machine
+set <addr> <value>   write a value to memory
+get <addr> [count]   show memory starting at an address
+input <value>...     queue input values
+run                  run until the program halts, needs input or hits a breakpoint
+out                  run until the next output (or halt/input)
+step [n]             execute n instructions (default 1)
+trace [n]            as step, listing each instruction's location
+break [addr]         set a breakpoint at an address or label, or list them
+delete <addr>        remove a breakpoint
+output               show and clear the output queue
+status               show ip, relative base and queues
+reset                restore the loaded program
+record               start recording inputs, outputs and sets
+save <file>          write the recorded session to a file
+loops                stop with an error on a guaranteed infinite loop
+history              list previous commands
+!!, !<n>             repeat the last or the n-th command
+help                 show this message
+quit                 leave the REPL";
+
+/// An interactive session around a single `Computer`.
+#[derive(Default)]
+pub struct Repl {
+    cpu: Option<Computer>,
+    history: Vec<String>,
+    breakpoints: BTreeSet<usize>,
+}
+
+impl Repl {
+    pub fn new() -> Repl {
+        Repl::default()
+    }
+
+    /// Loads a program, assembling or compiling it first if it's a `.asm` or
+    /// `.ic` file. Debug info comes from the source or a `<program>.dbg`
+    /// sidecar.
+    pub fn load(&mut self, path: &str) -> Result<String, String> {
+        let (instructions, debug) = if path.ends_with(".asm") {
+            let asm = assemble_file(path)?;
+            (asm.program, Some(asm.debug))
+        } else if path.ends_with(".ic") {
+            let asm = compile_file(path)?;
+            (asm.program, Some(asm.debug))
+        } else {
+            let sidecar = DebugInfo::sidecar(path);
+            let debug = if Path::new(&sidecar).exists() {
+                Some(DebugInfo::load(&sidecar)?)
+            } else {
+                None
+            };
+            (read_program(path)?, debug)
+        };
+        let len = instructions.len();
+        let mut cpu = Computer::new(instructions);
+        cpu.debug = debug;
+        self.cpu = Some(cpu);
+        self.breakpoints.clear();
+        Ok(format!("loaded {} ({} cells)", path, len))
+    }
+
+    /// Runs a single command line. Returns `None` when the session should end.
+    pub fn execute(&mut self, line: &str) -> Option<Result<String, String>> {
+        let line = line.trim();
+        if line.is_empty() {
+            return Some(Ok(String::new()));
+        }
+
+        let line = if line == "!!" {
+            match self.history.last() {
+                Some(previous) => previous.clone(),
+                None => return Some(Err("no previous command".to_string())),
+            }
+        } else if let Some(n) = line.strip_prefix('!') {
+            match n
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| self.history.get(n.wrapping_sub(1)))
+            {
+                Some(previous) => previous.clone(),
+                None => return Some(Err(format!("no command {} in history", n))),
+            }
+        } else {
+            line.to_string()
+        };
+        if line != "history" {
+            self.history.push(line.clone());
+        }
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let result = match words[0] {
+            "quit" | "exit" => return None,
+            "help" => Ok(HELP.to_string()),
+            "history" => Ok(self
+                .history
+                .iter()
+                .enumerate()
+                .map(|(i, h)| format!("{:>4}  {}", i + 1, h))
+                .collect::<Vec<_>>()
+                .join("\n")),
+            "load" => match words.get(1) {
+                Some(path) => self.load(path),
+                None => Err("usage: load <file>".to_string()),
+            },
+            command => self.machine_command(command, &words[1..]),
+        };
+        Some(result)
+    }
+
+    fn machine_command(&mut self, command: &str, args: &[&str]) -> Result<String, String> {
+        let cpu = self.cpu.as_mut().ok_or("no program loaded")?;
+        let breakpoints = &mut self.breakpoints;
+        match command {
+            "set" => {
+                let (address, value) = match args {
+                    [address, value] => (parse_address(address, cpu)?, parse_value(value)?),
+                    _ => return Err("usage: set <addr> <value>".to_string()),
+                };
+                cpu.set(address, value);
+                Ok(format!("[{}] = {}", address, value))
+            }
+            "get" => {
+                let (address, count) = match args {
+                    [address] => (parse_address(address, cpu)?, 1),
+                    [address, count] => (
+                        parse_address(address, cpu)?,
+                        count
+                            .parse::<usize>()
+                            .map_err(|e| format!("{}: {}", count, e))?,
+                    ),
+                    _ => return Err("usage: get <addr> [count]".to_string()),
+                };
+                let end = address.saturating_add(count).min(cpu.memory.len());
+                Ok((address..end)
+                    .map(|a| format!("[{}] = {}", a, cpu.memory[a]))
+                    .collect::<Vec<_>>()
+                    .join("\n"))
+            }
+            "input" => {
+                if args.is_empty() {
+                    return Err("usage: input <value>...".to_string());
+                }
+                for arg in args {
+                    cpu.push_input(parse_value(arg)?);
+                }
+                Ok(format!("input queue: {:?}", cpu.input))
+            }
+            "run" => {
+                let mut status = step(cpu)?;
+                while status.is_none() && !breakpoints.contains(&(cpu.ip as usize)) {
+                    status = step(cpu)?;
+                }
+                Ok(describe(cpu, status))
+            }
+            "out" => {
+                let outputs = cpu.output.len();
+                let mut status = None;
+                while status.is_none() && cpu.output.len() == outputs {
+                    status = step(cpu)?;
+                }
+                Ok(describe(cpu, status))
+            }
+            "step" | "trace" => {
+                let n = match args {
+                    [] => 1,
+                    [n] => n.parse::<usize>().map_err(|e| format!("{}: {}", n, e))?,
+                    _ => return Err(format!("usage: {} [n]", command)),
+                };
+                let mut status = None;
+                let mut trace = Vec::new();
+                for _ in 0..n {
+                    if command == "trace" {
+                        trace.push(cpu.location());
+                    }
+                    status = step(cpu)?;
+                    if status.is_some() {
+                        break;
+                    }
+                }
+                trace.push(describe(cpu, status));
+                Ok(trace.join("\n"))
+            }
+            "break" => match args {
+                [] => Ok(breakpoints
+                    .iter()
+                    .map(|a| location(cpu, *a))
+                    .collect::<Vec<_>>()
+                    .join("\n")),
+                [address] => {
+                    let address = parse_address(address, cpu)?;
+                    breakpoints.insert(address);
+                    Ok(format!("breakpoint at {}", location(cpu, address)))
+                }
+                _ => Err("usage: break [addr]".to_string()),
+            },
+            "delete" => match args {
+                [address] => {
+                    let address = parse_address(address, cpu)?;
+                    if !breakpoints.remove(&address) {
+                        return Err(format!("no breakpoint at {}", location(cpu, address)));
+                    }
+                    Ok(format!("deleted breakpoint at {}", location(cpu, address)))
+                }
+                _ => Err("usage: delete <addr>".to_string()),
+            },
+            "output" => Ok(format!("{:?}", cpu.output.drain(..).collect::<Vec<_>>())),
+            "status" => Ok(format!(
+                "ip={} base={} halted={} input={:?} output={:?}",
+                cpu.ip, cpu.base, cpu.halted, cpu.input, cpu.output
+            )),
+            "reset" => {
+                cpu.reset();
+                Ok("reset".to_string())
+            }
+            "record" => {
+                cpu.record();
+                Ok("recording".to_string())
+            }
+            "loops" => {
+                cpu.detect_loops();
+                Ok("detecting infinite loops".to_string())
+            }
+            "save" => {
+                let session = cpu.session.as_ref().ok_or("not recording")?;
+                let path = match args {
+                    [path] => path,
+                    _ => return Err("usage: save <file>".to_string()),
+                };
+                session.save(path).map_err(|e| format!("{}: {}", path, e))?;
+                Ok(format!("saved {} events to {}", session.events.len(), path))
+            }
+            command => Err(format!("unknown command: {} (try 'help')", command)),
+        }
+    }
+}
+
+fn parse_value(s: &str) -> Result<i64, String> {
+    s.parse::<i64>().map_err(|e| format!("{}: {}", s, e))
+}
+
+// A number, or a label if the program has debug info
+fn parse_address(s: &str, cpu: &Computer) -> Result<usize, String> {
+    let address = match &cpu.debug {
+        Some(debug) => debug.resolve(s).ok_or(format!("unknown address: {}", s))?,
+        None => s.parse::<usize>().map_err(|e| format!("{}: {}", s, e))?,
+    };
+    if address >= cpu.memory.len() {
+        return Err(format!("address out of range: {}", address));
+    }
+    Ok(address)
+}
+
+fn location(cpu: &Computer, address: usize) -> String {
+    match &cpu.debug {
+        Some(debug) => debug.location(address),
+        None => address.to_string(),
+    }
+}
+
+fn step(cpu: &mut Computer) -> Result<Option<Status>, String> {
+    cpu.try_step().map_err(|e| cpu.explain(&e))
+}
+
+fn describe(cpu: &Computer, status: Option<Status>) -> String {
+    let state = match status {
+        Some(Status::Halted) => "halted",
+        Some(Status::AwaitingInput) => "awaiting input",
+        Some(Status::Ready) | None => "paused",
+    };
+    let at = match &cpu.debug {
+        Some(_) => format!("ip={} {}", cpu.ip, cpu.location()),
+        None => format!("ip={}", cpu.ip),
+    };
+    format!("{} at {}, output={:?}", state, at, cpu.output)
+}
+
+pub fn run(program: Option<&str>) -> io::Result<()> {
+    let mut repl = Repl::new();
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+
+    if let Some(path) = program {
+        match repl.load(path) {
+            Ok(message) => writeln!(stdout, "{}", message)?,
+            Err(message) => writeln!(stdout, "error: {}", message)?,
+        }
+    }
+
+    loop {
+        write!(stdout, "> ")?;
+        stdout.flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            break;
+        }
+        match repl.execute(&line) {
+            None => break,
+            Some(Ok(message)) if message.is_empty() => (),
+            Some(Ok(message)) => writeln!(stdout, "{}", message)?,
+            Some(Err(message)) => writeln!(stdout, "error: {}", message)?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repl(program: Vec<i64>) -> Repl {
+        Repl {
+            cpu: Some(Computer::new(program)),
+            ..Repl::default()
+        }
+    }
+
+    fn ok(repl: &mut Repl, line: &str) -> String {
+        repl.execute(line).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_set_and_run() {
+        let mut repl = repl(vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50]);
+        assert_eq!(ok(&mut repl, "set 9 1"), "[9] = 1");
+        assert_eq!(ok(&mut repl, "run"), "halted at ip=9, output=[]");
+        assert_eq!(ok(&mut repl, "get 0 2"), "[0] = 2050\n[1] = 9");
+        assert_eq!(
+            ok(&mut repl, &format!("get 10 {}", usize::MAX)),
+            "[10] = 40\n[11] = 50"
+        );
+    }
+
+    #[test]
+    fn test_input_and_output() {
+        let mut repl = repl(vec![3, 0, 4, 0, 4, 0, 99]);
+        assert_eq!(ok(&mut repl, "run"), "awaiting input at ip=0, output=[]");
+        ok(&mut repl, "input 7");
+        assert_eq!(ok(&mut repl, "out"), "paused at ip=4, output=[7]");
+        assert_eq!(ok(&mut repl, "output"), "[7]");
+        assert_eq!(ok(&mut repl, "step 5"), "halted at ip=7, output=[7]");
+    }
+
+    #[test]
+    fn test_history() {
+        let mut repl = repl(vec![99]);
+        ok(&mut repl, "set 0 1");
+        ok(&mut repl, "get 0");
+        assert_eq!(ok(&mut repl, "!1"), "[0] = 1");
+        assert_eq!(ok(&mut repl, "!!"), "[0] = 1");
+        assert_eq!(
+            ok(&mut repl, "history"),
+            "   1  set 0 1\n   2  get 0\n   3  set 0 1\n   4  set 0 1"
+        );
+        assert!(repl.execute("!9").unwrap().is_err());
+        assert!(repl.execute("quit").is_none());
+    }
+
+    #[test]
+    fn test_record() {
+        let mut repl = repl(vec![3, 0, 4, 0, 99]);
+        assert!(repl.execute("save x").unwrap().is_err());
+        ok(&mut repl, "input 5");
+        ok(&mut repl, "step");
+        ok(&mut repl, "record");
+        ok(&mut repl, "set 0 6");
+        ok(&mut repl, "run");
+        let session = repl.cpu.as_ref().unwrap().session.as_ref().unwrap();
+        assert_eq!(session.to_string(), "set 0 0 6\nout 0 6\n");
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        let asm = crate::asm::assemble(
+            "count.asm",
+            "in n\nloop: out n\nadd n, #-1, n\njt n, #loop\nhlt\nn: data 0",
+        )
+        .unwrap();
+        let mut repl = repl(asm.program);
+        repl.cpu.as_mut().unwrap().debug = Some(asm.debug);
+        assert_eq!(
+            ok(&mut repl, "break loop+2"),
+            "breakpoint at loop+2 (count.asm:3)"
+        );
+        ok(&mut repl, "input 2");
+        assert_eq!(
+            ok(&mut repl, "run"),
+            "paused at ip=4 loop+2 (count.asm:3), output=[2]"
+        );
+        assert_eq!(
+            ok(&mut repl, "trace 2"),
+            "loop+2 (count.asm:3)\nloop+6 (count.asm:4)\npaused at ip=2 loop (count.asm:2), output=[2]"
+        );
+        ok(&mut repl, "delete 4");
+        assert!(repl.execute("delete 4").unwrap().is_err());
+        assert_eq!(
+            ok(&mut repl, "run"),
+            "halted at ip=12 n (count.asm:6), output=[2, 1]"
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut repl = Repl::new();
+        assert_eq!(
+            repl.execute("run").unwrap(),
+            Err("no program loaded".to_string())
+        );
+        let mut repl = self::repl(vec![99]);
+        assert!(repl.execute("set 0").unwrap().is_err());
+        assert!(repl.execute("get 999999").unwrap().is_err());
+        assert!(repl.execute("bogus").unwrap().is_err());
+
+        let mut repl = self::repl(vec![1, 0, 0, 0, 42]);
+        assert_eq!(
+            repl.execute("run").unwrap(),
+            Err("Unrecognised opcode: 42, ip=4".to_string())
+        );
+
+        let mut repl = self::repl(vec![1105, 1, 0]);
+        ok(&mut repl, "loops");
+        assert_eq!(
+            repl.execute("run").unwrap(),
+            Err("Infinite loop over addresses 0..=0, ip=0".to_string())
+        );
+    }
+}
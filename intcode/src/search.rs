@@ -0,0 +1,547 @@
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use crate::machine::Machine;
+use crate::{Computer, Error};
+
+/// An ordered set of candidate vectors. Candidates are addressed by index so
+/// worker threads can share the space without sharing an iterator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Space {
+    /// Every combination of one value from each set, last set varying fastest.
+    Product(Vec<Vec<i64>>),
+    /// Every ordering of `k` distinct items from `values`, in the same order
+    /// as itertools' `permutations`.
+    Permutations(Vec<i64>, usize),
+}
+
+impl Space {
+    pub fn ranges(ranges: &[RangeInclusive<i64>]) -> Space {
+        Space::Product(ranges.iter().map(|r| r.clone().collect()).collect())
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Space::Product(sets) => sets.iter().map(|s| s.len()).product(),
+            Space::Permutations(values, k) => permutations(values.len(), *k),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The length of each candidate.
+    pub fn depth(&self) -> usize {
+        match self {
+            Space::Product(sets) => sets.len(),
+            Space::Permutations(_, k) => *k,
+        }
+    }
+
+    /// The space of the first `depth` elements of each candidate, in the
+    /// same order.
+    pub fn prefixes(&self, depth: usize) -> Space {
+        match self {
+            Space::Product(sets) => Space::Product(sets[..depth].to_vec()),
+            Space::Permutations(values, _) => Space::Permutations(values.clone(), depth),
+        }
+    }
+
+    /// The values that can follow `prefix`, in candidate order.
+    pub fn choices(&self, prefix: &[i64]) -> Vec<i64> {
+        match self {
+            Space::Product(sets) => sets[prefix.len()].clone(),
+            Space::Permutations(values, _) => {
+                let mut pool = values.clone();
+                for value in prefix {
+                    if let Some(i) = pool.iter().position(|v| v == value) {
+                        pool.remove(i);
+                    }
+                }
+                pool
+            }
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Vec<i64> {
+        match self {
+            Space::Product(sets) => {
+                let mut index = index;
+                let mut candidate = vec![0; sets.len()];
+                for (i, set) in sets.iter().enumerate().rev() {
+                    candidate[i] = set[index % set.len()];
+                    index /= set.len();
+                }
+                candidate
+            }
+            Space::Permutations(values, k) => {
+                let mut index = index;
+                let mut pool = values.clone();
+                let mut candidate = Vec::with_capacity(*k);
+                for i in 0..*k {
+                    let count = permutations(pool.len() - 1, k - i - 1);
+                    candidate.push(pool.remove(index / count));
+                    index %= count;
+                }
+                candidate
+            }
+        }
+    }
+}
+
+// n! / (n - k)!
+fn permutations(n: usize, k: usize) -> usize {
+    if k > n {
+        0
+    } else {
+        (n - k + 1..=n).product()
+    }
+}
+
+pub fn default_threads() -> usize {
+    thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+}
+
+/// Returns the earliest candidate in `space` for which `matches` is true.
+/// Threads stop picking up work as soon as a match is known, and the result
+/// is the same as a sequential scan whatever the thread count.
+pub fn find_first<F>(space: &Space, threads: usize, matches: F) -> Option<Vec<i64>>
+where
+    F: Fn(&[i64]) -> bool + Sync,
+{
+    let len = space.len();
+    let next = AtomicUsize::new(0);
+    let found = AtomicUsize::new(usize::MAX);
+
+    thread::scope(|s| {
+        for _ in 0..threads.max(1) {
+            s.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                if index >= len || index > found.load(Ordering::Relaxed) {
+                    break;
+                }
+                if matches(&space.get(index)) {
+                    found.fetch_min(index, Ordering::Relaxed);
+                    break;
+                }
+            });
+        }
+    });
+
+    match found.into_inner() {
+        usize::MAX => None,
+        index => Some(space.get(index)),
+    }
+}
+
+/// Returns every candidate in `space` for which `matches` is true, in order.
+pub fn find_all<F>(space: &Space, threads: usize, matches: F) -> Vec<Vec<i64>>
+where
+    F: Fn(&[i64]) -> bool + Sync,
+{
+    let len = space.len();
+    let next = AtomicUsize::new(0);
+    let found = Mutex::new(Vec::new());
+
+    thread::scope(|s| {
+        for _ in 0..threads.max(1) {
+            s.spawn(|| {
+                let mut local = Vec::new();
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    if index >= len {
+                        break;
+                    }
+                    if matches(&space.get(index)) {
+                        local.push(index);
+                    }
+                }
+                found.lock().unwrap().extend(local);
+            });
+        }
+    });
+
+    let mut found = found.into_inner().unwrap();
+    found.sort_unstable();
+    found.into_iter().map(|index| space.get(index)).collect()
+}
+
+/// Returns the candidate with the highest score, ignoring candidates scored
+/// `None`. Ties go to the earliest candidate.
+pub fn find_best<K, F>(space: &Space, threads: usize, score: F) -> Option<(Vec<i64>, K)>
+where
+    K: Ord + Send,
+    F: Fn(&[i64]) -> Option<K> + Sync,
+{
+    let len = space.len();
+    let next = AtomicUsize::new(0);
+    let best: Mutex<Option<(K, usize)>> = Mutex::new(None);
+
+    thread::scope(|s| {
+        for _ in 0..threads.max(1) {
+            s.spawn(|| {
+                let mut local: Option<(K, usize)> = None;
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    if index >= len {
+                        break;
+                    }
+                    if let Some(k) = score(&space.get(index)) {
+                        local = better(local, (k, index));
+                    }
+                }
+                if let Some(local) = local {
+                    let mut best = best.lock().unwrap();
+                    *best = better(best.take(), local);
+                }
+            });
+        }
+    });
+
+    best.into_inner()
+        .unwrap()
+        .map(|(k, index)| (space.get(index), k))
+}
+
+/// As `find_best`, for scores built up one element at a time. `step` takes
+/// the state after a prefix and the next element and returns the state after
+/// both, or `None` to rule out everything below it; `score` scores the state
+/// after a whole candidate. Candidates that share a prefix share the steps
+/// for it, so the space is walked as a tree: a state can be a snapshot of a
+/// machine, or just what it left behind, like day07's signal.
+///
+/// The tree is split into subtrees at the shallowest depth that gives every
+/// thread something to do, each walked depth first by one thread. Ties go to
+/// the earliest candidate, as with `find_best`.
+pub fn find_best_prefix<S, K, F, G>(
+    space: &Space,
+    threads: usize,
+    root: S,
+    step: F,
+    score: G,
+) -> Option<(Vec<i64>, K)>
+where
+    S: Sync,
+    K: Ord + Send,
+    F: Fn(&S, i64) -> Option<S> + Sync,
+    G: Fn(&S) -> Option<K> + Sync,
+{
+    let threads = threads.max(1);
+    let split = (0..=space.depth())
+        .find(|d| space.prefixes(*d).len() >= threads)
+        .unwrap_or_else(|| space.depth());
+    let prefixes = space.prefixes(split);
+    let walk = Walk {
+        space,
+        step: &step,
+        score: &score,
+    };
+    let next = AtomicUsize::new(0);
+    let best: Mutex<Option<(K, usize)>> = Mutex::new(None);
+
+    thread::scope(|s| {
+        for _ in 0..threads {
+            s.spawn(|| {
+                let mut local: Option<(K, usize)> = None;
+                loop {
+                    let item = next.fetch_add(1, Ordering::Relaxed);
+                    if item >= prefixes.len() {
+                        break;
+                    }
+                    let mut index = item * walk.subtree(split);
+                    let fixed = prefixes.get(item);
+                    walk.visit(&root, &mut Vec::new(), &fixed, &mut index, &mut local);
+                }
+                if let Some(local) = local {
+                    let mut best = best.lock().unwrap();
+                    *best = better(best.take(), local);
+                }
+            });
+        }
+    });
+
+    best.into_inner()
+        .unwrap()
+        .map(|(k, index)| (space.get(index), k))
+}
+
+struct Walk<'a, F, G> {
+    space: &'a Space,
+    step: &'a F,
+    score: &'a G,
+}
+
+impl<F, G> Walk<'_, F, G> {
+    // The number of candidates below a prefix of length `depth`
+    fn subtree(&self, depth: usize) -> usize {
+        self.space.len() / self.space.prefixes(depth).len().max(1)
+    }
+
+    // Scores every candidate below `prefix` that starts with `fixed`, where
+    // `state` is the state after `prefix` and `index` is the index of the
+    // first such candidate, leaving `index` just past them
+    fn visit<S, K: Ord>(
+        &self,
+        state: &S,
+        prefix: &mut Vec<i64>,
+        fixed: &[i64],
+        index: &mut usize,
+        best: &mut Option<(K, usize)>,
+    ) where
+        F: Fn(&S, i64) -> Option<S>,
+        G: Fn(&S) -> Option<K>,
+    {
+        if prefix.len() == self.space.depth() {
+            if let Some(k) = (self.score)(state) {
+                *best = better(best.take(), (k, *index));
+            }
+            *index += 1;
+            return;
+        }
+        let choices = match fixed.get(prefix.len()) {
+            Some(value) => vec![*value],
+            None => self.space.choices(prefix),
+        };
+        for value in choices {
+            match (self.step)(state, value) {
+                Some(next) => {
+                    prefix.push(value);
+                    self.visit(&next, prefix, fixed, index, best);
+                    prefix.pop();
+                }
+                None => *index += self.subtree(prefix.len() + 1),
+            }
+        }
+    }
+}
+
+fn better<K: Ord>(current: Option<(K, usize)>, candidate: (K, usize)) -> Option<(K, usize)> {
+    match current {
+        Some(current)
+            if current.0 > candidate.0 || (current.0 == candidate.0 && current.1 < candidate.1) =>
+        {
+            Some(current)
+        }
+        _ => Some(candidate),
+    }
+}
+
+/// How a candidate vector is applied to the program before it runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Params {
+    /// Written to these addresses, e.g. day02's noun and verb at 1 and 2.
+    Memory(Vec<usize>),
+    /// Pushed onto the input queue in order.
+    Input,
+}
+
+/// A search over a single program: each candidate is applied according to
+/// `params` to a fork of the loaded program, which is run until it halts or
+/// waits for input and then handed to the goal. Candidates whose run fails
+/// never match.
+pub struct Search {
+    template: Computer,
+    params: Params,
+    space: Space,
+    pub threads: usize,
+}
+
+impl Search {
+    pub fn new(program: &[i64], params: Params, space: Space) -> Search {
+        Search {
+            template: Computer::new(program.to_vec()),
+            params,
+            space,
+            threads: default_threads(),
+        }
+    }
+
+    pub fn run(&self, candidate: &[i64]) -> Result<Computer, Error> {
+        let mut cpu = self.template.fork();
+        match &self.params {
+            Params::Memory(addresses) => {
+                for (address, value) in addresses.iter().zip(candidate) {
+                    Machine::write(&mut cpu, *address, *value);
+                }
+            }
+            Params::Input => candidate.iter().for_each(|v| cpu.push_input(*v)),
+        }
+        cpu.try_run()?;
+        Ok(cpu)
+    }
+
+    pub fn first<P>(&self, goal: P) -> Option<Vec<i64>>
+    where
+        P: Fn(&Computer) -> bool + Sync,
+    {
+        find_first(&self.space, self.threads, |c| {
+            self.run(c).is_ok_and(|cpu| goal(&cpu))
+        })
+    }
+
+    pub fn best<K, F>(&self, score: F) -> Option<(Vec<i64>, K)>
+    where
+        K: Ord + Send,
+        F: Fn(&Computer) -> Option<K> + Sync,
+    {
+        find_best(&self.space, self.threads, |c| {
+            self.run(c).ok().and_then(|cpu| score(&cpu))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_instructions;
+    use std::fs;
+
+    #[test]
+    fn test_space() {
+        let space = Space::ranges(&[0..=2, 5..=6]);
+        assert_eq!(space.len(), 6);
+        assert_eq!(space.get(0), vec![0, 5]);
+        assert_eq!(space.get(1), vec![0, 6]);
+        assert_eq!(space.get(5), vec![2, 6]);
+
+        let space = Space::Permutations(vec![0, 1, 2, 3], 3);
+        assert_eq!(space.len(), 24);
+        assert_eq!(space.get(0), vec![0, 1, 2]);
+        assert_eq!(space.get(1), vec![0, 1, 3]);
+        assert_eq!(space.get(23), vec![3, 2, 1]);
+        assert!(Space::Permutations(vec![0, 1], 3).is_empty());
+    }
+
+    #[test]
+    fn test_find_first() {
+        let space = Space::ranges(&[0..=99, 0..=99]);
+        for threads in 1..=4 {
+            let found = find_first(&space, threads, |c| c[0] * c[1] == 12);
+            assert_eq!(found, Some(vec![1, 12]));
+        }
+        assert_eq!(find_first(&space, 4, |c| c[0] > 100), None);
+    }
+
+    #[test]
+    fn test_find_all() {
+        let space = Space::ranges(&[0..=99, 0..=99]);
+        for threads in 1..=4 {
+            let found = find_all(&space, threads, |c| c[0] * c[1] == 12);
+            assert_eq!(
+                found,
+                vec![
+                    vec![1, 12],
+                    vec![2, 6],
+                    vec![3, 4],
+                    vec![4, 3],
+                    vec![6, 2],
+                    vec![12, 1]
+                ]
+            );
+        }
+        assert!(find_all(&space, 4, |c| c[0] > 100).is_empty());
+    }
+
+    #[test]
+    fn test_find_best() {
+        let space = Space::Permutations(vec![1, 2, 3], 3);
+        let best = find_best(&space, 3, |c| Some(c[0] * 100 + c[1] * 10 + c[2]));
+        assert_eq!(best, Some((vec![3, 2, 1], 321)));
+        let best = find_best(&space, 3, |c| Some(c[0]));
+        assert_eq!(best, Some((vec![3, 1, 2], 3)));
+    }
+
+    #[test]
+    fn test_find_best_prefix() {
+        // Digits read as a number, built up one digit at a time
+        let steps = AtomicUsize::new(0);
+        let step = |n: &i64, d: i64| {
+            steps.fetch_add(1, Ordering::Relaxed);
+            Some(n * 10 + d)
+        };
+        let space = Space::Permutations(vec![1, 2, 3, 4, 5], 5);
+        for threads in 1..=8 {
+            let best = find_best_prefix(&space, threads, 0, step, |n| Some(n % 1000));
+            assert_eq!(
+                best,
+                find_best(&space, threads, |c| Some(c[2] * 100 + c[3] * 10 + c[4]))
+            );
+            assert_eq!(best, Some((vec![1, 2, 5, 4, 3], 543)));
+        }
+        // One step per node of the tree rather than five per candidate,
+        // less anything repeated above the split
+        steps.store(0, Ordering::Relaxed);
+        find_best_prefix(&space, 1, 0, step, |n| Some(*n));
+        assert_eq!(steps.load(Ordering::Relaxed), 5 + 20 + 60 + 120 + 120);
+
+        // Pruned subtrees still count towards the indices of the rest
+        let space = Space::ranges(&[0..=3, 0..=3]);
+        let best = find_best_prefix(
+            &space,
+            3,
+            0,
+            |n, d| if *n == 0 && d < 2 { None } else { Some(n + d) },
+            |n| Some(*n % 4),
+        );
+        assert_eq!(best, Some((vec![2, 1], 3)));
+    }
+
+    #[test]
+    fn test_day02() {
+        let input = fs::read_to_string("../day02/data/input.txt").expect("Error reading file");
+        let instructions = parse_instructions(&input).unwrap();
+        let search = Search::new(
+            &instructions,
+            Params::Memory(vec![1, 2]),
+            Space::ranges(&[0..=99, 0..=99]),
+        );
+        assert_eq!(search.run(&[12, 2]).unwrap().memory[0], 4484226);
+        assert_eq!(
+            search.first(|cpu| cpu.memory[0] == 19690720),
+            Some(vec![56, 96])
+        );
+    }
+
+    #[test]
+    fn test_input() {
+        // day05: output 999 below 8, 1000 for 8 and 1001 above
+        let program = vec![
+            3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36, 98, 0,
+            0, 1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000, 1, 20, 4,
+            20, 1105, 1, 46, 98, 99,
+        ];
+        let search = Search::new(&program, Params::Input, Space::ranges(&[0..=20]));
+        assert_eq!(search.first(|cpu| cpu.output == vec![1000]), Some(vec![8]));
+        let best = search.best(|cpu| cpu.output.last().cloned());
+        assert_eq!(best, Some((vec![9], 1001)));
+    }
+
+    #[test]
+    fn test_failures() {
+        // Jumps to the candidate and runs it as an instruction
+        let search = Search::new(
+            &[1105, 1, 4, 99, 0],
+            Params::Memory(vec![4]),
+            Space::ranges(&[97..=99]),
+        );
+        assert_eq!(
+            search.run(&[98]).err(),
+            Some(Error::UnknownOpcode { opcode: 98, ip: 4 })
+        );
+        assert_eq!(search.first(|_| true), Some(vec![99]));
+        assert_eq!(search.best(|cpu| Some(cpu.ip)), Some((vec![99], 5)));
+
+        // Addresses past the end of the program grow its memory
+        let search = Search::new(
+            &[1, 10, 11, 0, 99],
+            Params::Memory(vec![10, 11]),
+            Space::ranges(&[0..=9, 0..=9]),
+        );
+        assert_eq!(search.first(|cpu| cpu.memory[0] == 17), Some(vec![8, 9]));
+    }
+}
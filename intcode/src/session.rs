@@ -0,0 +1,312 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::str::FromStr;
+
+use crate::{Computer, Error};
+
+/// Something the machine exchanged with the outside world, stamped with the
+/// number of instructions executed since recording started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Input {
+        step: u64,
+        value: i64,
+    },
+    Output {
+        step: u64,
+        value: i64,
+    },
+    /// A memory cell written from outside the program, e.g. by the REPL.
+    Set {
+        step: u64,
+        address: usize,
+        value: i64,
+    },
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Event::Input { step, value } => write!(f, "in {} {}", step, value),
+            Event::Output { step, value } => write!(f, "out {} {}", step, value),
+            Event::Set {
+                step,
+                address,
+                value,
+            } => write!(f, "set {} {} {}", step, address, value),
+        }
+    }
+}
+
+impl FromStr for Event {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Event, String> {
+        let words: Vec<&str> = s.split_whitespace().collect();
+        let (kind, step, address, value) = match words[..] {
+            ["set", step, address, value] => ("set", step, Some(address), value),
+            [kind, step, value] => (kind, step, None, value),
+            _ => return Err(format!("Malformed event: {}", s)),
+        };
+        let step = step.parse().map_err(|e| format!("{}: {}", s, e))?;
+        let value = value.parse().map_err(|e| format!("{}: {}", s, e))?;
+        match (kind, address) {
+            ("in", None) => Ok(Event::Input { step, value }),
+            ("out", None) => Ok(Event::Output { step, value }),
+            ("set", Some(address)) => {
+                let address = address.parse().map_err(|e| format!("{}: {}", s, e))?;
+                Ok(Event::Set {
+                    step,
+                    address,
+                    value,
+                })
+            }
+            ("set", None) => Err(format!("Malformed event: {}", s)),
+            _ => Err(format!("Unknown event: {}", kind)),
+        }
+    }
+}
+
+/// The I/O history of a run. The file format is one event per line, e.g.
+/// `in 0 5`, `out 1234 652726` or `set 0 1 12` (step, address, value); blank
+/// lines and `#` comments are ignored.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Session {
+    pub events: Vec<Event>,
+}
+
+/// Where a replay first went differently from the recording.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    pub index: usize,
+    pub expected: Option<Event>,
+    pub actual: Option<Event>,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let show = |e: &Option<Event>| match e {
+            Some(e) => e.to_string(),
+            None => "nothing".to_string(),
+        };
+        write!(
+            f,
+            "Replay diverged at event {}: expected {}, got {}",
+            self.index,
+            show(&self.expected),
+            show(&self.actual)
+        )
+    }
+}
+
+/// Why a replay failed: the program hit an error, or it went differently
+/// from the recording.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayError {
+    Failed(Error),
+    Diverged(Mismatch),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Failed(e) => write!(f, "Replay failed: {}", e),
+            ReplayError::Diverged(mismatch) => write!(f, "{}", mismatch),
+        }
+    }
+}
+
+impl Session {
+    pub fn load(path: &str) -> Result<Session, String> {
+        let contents = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        contents.parse()
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    pub fn inputs(&self) -> Vec<i64> {
+        self.events
+            .iter()
+            .filter_map(|e| match e {
+                Event::Input { value, .. } => Some(*value),
+                Event::Output { .. } | Event::Set { .. } => None,
+            })
+            .collect()
+    }
+
+    /// Runs `program` fed with the recorded inputs, making the recorded
+    /// writes at the recorded steps, and checks that it consumes and produces
+    /// exactly the recorded events.
+    pub fn replay(&self, program: &[i64]) -> Result<(), ReplayError> {
+        let mut cpu = Computer::new(program.to_vec());
+        cpu.record();
+        for value in self.inputs() {
+            cpu.push_input(value);
+        }
+        let mut sets = self
+            .events
+            .iter()
+            .filter_map(|e| match e {
+                Event::Set {
+                    step,
+                    address,
+                    value,
+                } => Some((*step, *address, *value)),
+                _ => None,
+            })
+            .peekable();
+        loop {
+            while let Some((_, address, value)) = sets.next_if(|s| s.0 <= cpu.steps) {
+                cpu.set(address, value);
+            }
+            let stopped = cpu.try_step().map_err(ReplayError::Failed)?.is_some();
+            if stopped && sets.peek().is_none_or(|s| s.0 > cpu.steps) {
+                break;
+            }
+        }
+        let actual = cpu.session.unwrap().events;
+
+        let len = self.events.len().max(actual.len());
+        match (0..len).find(|&i| self.events.get(i) != actual.get(i)) {
+            Some(index) => Err(ReplayError::Diverged(Mismatch {
+                index,
+                expected: self.events.get(index).cloned(),
+                actual: actual.get(index).cloned(),
+            })),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for Session {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for event in &self.events {
+            writeln!(f, "{}", event)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Session {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Session, String> {
+        let events = s
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(str::parse)
+            .collect::<Result<_, _>>()?;
+        Ok(Session { events })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Status;
+
+    // Outputs 999 below 8, 1000 for 8 and 1001 above
+    const COMPARE: [i64; 47] = [
+        3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36, 98, 0, 0,
+        1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000, 1, 20, 4, 20,
+        1105, 1, 46, 98, 99,
+    ];
+
+    #[test]
+    fn test_record() {
+        let mut cpu = Computer::new(COMPARE.to_vec());
+        cpu.record();
+        assert_eq!(cpu.try_run(), Ok(Status::AwaitingInput));
+        cpu.push_input(8);
+        assert_eq!(cpu.try_run(), Ok(Status::Halted));
+        let session = cpu.session.take().unwrap();
+        assert_eq!(
+            session.events,
+            vec![
+                Event::Input { step: 0, value: 8 },
+                Event::Output {
+                    step: 4,
+                    value: 1000
+                }
+            ]
+        );
+        assert_eq!(session.to_string(), "in 0 8\nout 4 1000\n");
+        assert_eq!("# comment\nin 0 8\n\nout 4 1000".parse(), Ok(session));
+    }
+
+    #[test]
+    fn test_replay() {
+        let session: Session = "in 0 7\nout 5 999".parse().unwrap();
+        assert_eq!(session.replay(&COMPARE), Ok(()));
+
+        let session: Session = "in 0 9\nout 5 999".parse().unwrap();
+        assert_eq!(
+            session.replay(&COMPARE),
+            Err(ReplayError::Diverged(Mismatch {
+                index: 1,
+                expected: Some(Event::Output {
+                    step: 5,
+                    value: 999
+                }),
+                actual: Some(Event::Output {
+                    step: 7,
+                    value: 1001
+                }),
+            }))
+        );
+
+        // Stores the input over its own first cell, then runs into opcode 98
+        let session: Session = "in 0 5".parse().unwrap();
+        assert_eq!(
+            session.replay(&[3, 0, 98]),
+            Err(ReplayError::Failed(Error::UnknownOpcode {
+                opcode: 98,
+                ip: 2
+            }))
+        );
+
+        // Compares with 7 instead of 8, then clears a cell once it has halted
+        let session: Session = "set 0 4 7\nin 0 7\nout 4 875\nset 6 0 0".parse().unwrap();
+        assert_eq!(session.replay(&COMPARE), Ok(()));
+        let session: Session = "in 0 7\nout 5 999\nset 9 0 0".parse().unwrap();
+        assert!(matches!(
+            session.replay(&COMPARE),
+            Err(ReplayError::Diverged(Mismatch { index: 2, .. }))
+        ));
+    }
+
+    #[test]
+    fn test_record_mid_run() {
+        let mut cpu = Computer::new(COMPARE.to_vec());
+        cpu.push_input(3);
+        for _ in 0..3 {
+            cpu.try_step().unwrap();
+        }
+        cpu.record();
+        cpu.set(45, 99);
+        assert_eq!(cpu.try_run(), Ok(Status::Halted));
+        assert_eq!(cpu.session.unwrap().to_string(), "set 0 45 99\nout 2 999\n");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!("in 0".parse::<Session>().is_err());
+        assert!("at 0 1".parse::<Session>().is_err());
+        assert!("in x 1".parse::<Session>().is_err());
+        assert!("set 0 1".parse::<Session>().is_err());
+        assert!("set 0 -1 1".parse::<Session>().is_err());
+        assert!("in 0 1 2".parse::<Session>().is_err());
+        assert_eq!(
+            "set 3 1 -2".parse(),
+            Ok(Event::Set {
+                step: 3,
+                address: 1,
+                value: -2
+            })
+        );
+    }
+}
@@ -0,0 +1,516 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::ops::{Add, Mul, Neg, Sub};
+
+/// A symbolic integer variable, identified by its index in a `Solver`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Var(pub usize);
+
+impl fmt::Display for Var {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "v{}", self.0)
+    }
+}
+
+/// A polynomial over `Var`s with integer coefficients. Each key is a monomial,
+/// stored as a sorted list of variables; the empty monomial is the constant.
+/// A coefficient that overflows `i64` poisons the expression: it has no value
+/// and any constraint on it is unsatisfiable.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Expr {
+    terms: BTreeMap<Vec<Var>, i64>,
+    overflow: bool,
+}
+
+impl Expr {
+    pub fn constant(value: i64) -> Expr {
+        let mut expr = Expr::default();
+        expr.add_term(Vec::new(), Some(value));
+        expr
+    }
+
+    pub fn var(var: Var) -> Expr {
+        let mut expr = Expr::default();
+        expr.add_term(vec![var], Some(1));
+        expr
+    }
+
+    pub fn as_constant(&self) -> Option<i64> {
+        if self.overflow {
+            return None;
+        }
+        match self.terms.len() {
+            0 => Some(0),
+            1 => self.terms.get(&Vec::new()).cloned(),
+            _ => None,
+        }
+    }
+
+    pub fn vars(&self) -> BTreeSet<Var> {
+        self.terms.keys().flatten().cloned().collect()
+    }
+
+    /// The value of the expression, or `None` if it doesn't fit in an `i64`.
+    pub fn eval(&self, values: &[i64]) -> Option<i64> {
+        if self.overflow {
+            return None;
+        }
+        self.terms.iter().try_fold(0i64, |sum, (monomial, c)| {
+            let term = monomial
+                .iter()
+                .try_fold(*c, |acc, v| acc.checked_mul(values[v.0]))?;
+            sum.checked_add(term)
+        })
+    }
+
+    fn add_term(&mut self, monomial: Vec<Var>, coefficient: Option<i64>) {
+        let c = self.terms.entry(monomial.clone()).or_insert(0);
+        match coefficient.and_then(|coefficient| c.checked_add(coefficient)) {
+            Some(sum) => *c = sum,
+            None => self.overflow = true,
+        }
+        if *c == 0 {
+            self.terms.remove(&monomial);
+        }
+    }
+}
+
+impl Add for &Expr {
+    type Output = Expr;
+
+    fn add(self, other: &Expr) -> Expr {
+        let mut result = Expr {
+            terms: self.terms.clone(),
+            overflow: self.overflow || other.overflow,
+        };
+        for (monomial, c) in &other.terms {
+            result.add_term(monomial.clone(), Some(*c));
+        }
+        result
+    }
+}
+
+impl Neg for &Expr {
+    type Output = Expr;
+
+    fn neg(self) -> Expr {
+        let mut result = Expr {
+            overflow: self.overflow,
+            ..Expr::default()
+        };
+        for (monomial, c) in &self.terms {
+            result.add_term(monomial.clone(), c.checked_neg());
+        }
+        result
+    }
+}
+
+impl Sub for &Expr {
+    type Output = Expr;
+
+    fn sub(self, other: &Expr) -> Expr {
+        self + &-other
+    }
+}
+
+impl Mul for &Expr {
+    type Output = Expr;
+
+    fn mul(self, other: &Expr) -> Expr {
+        let mut result = Expr {
+            overflow: self.overflow || other.overflow,
+            ..Expr::default()
+        };
+        for (m1, c1) in &self.terms {
+            for (m2, c2) in &other.terms {
+                let mut monomial = m1.clone();
+                monomial.extend(m2);
+                monomial.sort();
+                result.add_term(monomial, c1.checked_mul(*c2));
+            }
+        }
+        result
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.terms.is_empty() {
+            return write!(f, "0");
+        }
+        for (i, (monomial, c)) in self.terms.iter().rev().enumerate() {
+            let sign = if *c < 0 { "-" } else { "+" };
+            if i == 0 {
+                if *c < 0 {
+                    write!(f, "-")?;
+                }
+            } else {
+                write!(f, " {} ", sign)?;
+            }
+            let c = c.abs();
+            if monomial.is_empty() || c != 1 {
+                write!(f, "{}", c)?;
+            }
+            for (j, v) in monomial.iter().enumerate() {
+                if j > 0 || c != 1 {
+                    write!(f, "*")?;
+                }
+                write!(f, "{}", v)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How a constraint's expression relates to zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Relation {
+    Eq,
+    Ne,
+    Le,
+}
+
+/// `expr <relation> 0`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Constraint {
+    pub expr: Expr,
+    pub relation: Relation,
+}
+
+impl Constraint {
+    pub fn eq(x: &Expr, y: &Expr) -> Constraint {
+        Constraint {
+            expr: x - y,
+            relation: Relation::Eq,
+        }
+    }
+
+    pub fn ne(x: &Expr, y: &Expr) -> Constraint {
+        Constraint {
+            expr: x - y,
+            relation: Relation::Ne,
+        }
+    }
+
+    pub fn lt(x: &Expr, y: &Expr) -> Constraint {
+        Constraint {
+            expr: &(x - y) + &Expr::constant(1),
+            relation: Relation::Le,
+        }
+    }
+
+    pub fn ge(x: &Expr, y: &Expr) -> Constraint {
+        Constraint {
+            expr: y - x,
+            relation: Relation::Le,
+        }
+    }
+
+    /// Whether the constraint is met by `values`; never if the expression
+    /// overflows.
+    pub fn holds(&self, values: &[i64]) -> bool {
+        match (self.expr.eval(values), self.relation) {
+            (None, _) => false,
+            (Some(value), Relation::Eq) => value == 0,
+            (Some(value), Relation::Ne) => value != 0,
+            (Some(value), Relation::Le) => value <= 0,
+        }
+    }
+}
+
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let relation = match self.relation {
+            Relation::Eq => "==",
+            Relation::Ne => "!=",
+            Relation::Le => "<=",
+        };
+        write!(f, "{} {} 0", self.expr, relation)
+    }
+}
+
+type Domains = Vec<(i64, i64)>;
+
+// Interval propagation gives up after this many passes without a fixpoint and
+// leaves the rest to the search.
+const MAX_PROPAGATION_PASSES: usize = 1000;
+
+/// A small integer constraint solver over bounded variables. Linear terms are
+/// narrowed by interval propagation; everything else is settled by bisecting
+/// the smallest remaining domain and checking the constraints once every
+/// variable is fixed.
+#[derive(Debug, Clone, Default)]
+pub struct Solver {
+    domains: Domains,
+    constraints: Vec<Constraint>,
+}
+
+impl Solver {
+    pub fn new() -> Solver {
+        Solver::default()
+    }
+
+    pub fn add_var(&mut self, min: i64, max: i64) -> Var {
+        self.domains.push((min, max));
+        Var(self.domains.len() - 1)
+    }
+
+    pub fn domain(&self, var: Var) -> (i64, i64) {
+        self.domains[var.0]
+    }
+
+    pub fn add(&mut self, constraint: Constraint) {
+        self.constraints.push(constraint);
+    }
+
+    pub fn constraints(&self) -> &[Constraint] {
+        &self.constraints
+    }
+
+    /// Cheap check used to prune branches: `false` means there is definitely
+    /// no solution, `true` only that propagation found no contradiction.
+    pub fn is_feasible(&self) -> bool {
+        self.propagate(self.domains.clone()).is_some()
+    }
+
+    pub fn solve(&self) -> Option<Vec<i64>> {
+        self.solve_all(1).pop()
+    }
+
+    /// Returns up to `limit` solutions, sorted.
+    pub fn solve_all(&self, limit: usize) -> Vec<Vec<i64>> {
+        let mut solutions = Vec::new();
+        if limit > 0 {
+            self.search(self.domains.clone(), limit, &mut solutions);
+        }
+        solutions.sort();
+        solutions
+    }
+
+    fn search(&self, domains: Domains, limit: usize, solutions: &mut Vec<Vec<i64>>) {
+        let domains = match self.propagate(domains) {
+            Some(domains) => domains,
+            None => return,
+        };
+        let split = domains
+            .iter()
+            .enumerate()
+            .filter(|(_, (min, max))| min < max)
+            .min_by_key(|(_, (min, max))| *max as i128 - *min as i128)
+            .map(|(i, _)| i);
+
+        match split {
+            None => {
+                let values: Vec<i64> = domains.iter().map(|(min, _)| *min).collect();
+                if self.constraints.iter().all(|c| c.holds(&values)) {
+                    solutions.push(values);
+                }
+            }
+            Some(i) => {
+                let (min, max) = domains[i];
+                let mid = ((min as i128 + max as i128).div_euclid(2)) as i64;
+                for half in &[(min, mid), (mid + 1, max)] {
+                    let mut domains = domains.clone();
+                    domains[i] = *half;
+                    self.search(domains, limit, solutions);
+                    if solutions.len() >= limit {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    fn propagate(&self, mut domains: Domains) -> Option<Domains> {
+        for _ in 0..MAX_PROPAGATION_PASSES {
+            let mut changed = false;
+            for constraint in &self.constraints {
+                changed |= narrow(constraint, &mut domains)?;
+            }
+            if !changed {
+                break;
+            }
+        }
+        Some(domains)
+    }
+}
+
+// Narrows the domains of the linear variables in `constraint`, returning
+// whether anything changed or `None` if the constraint cannot be satisfied.
+fn narrow(constraint: &Constraint, domains: &mut Domains) -> Option<bool> {
+    if constraint.expr.overflow {
+        return None;
+    }
+    let bounds: Vec<(i128, i128)> = constraint
+        .expr
+        .terms
+        .iter()
+        .map(|(monomial, c)| term_bounds(monomial, *c, domains))
+        .collect();
+    let min: i128 = bounds.iter().map(|b| b.0).fold(0, i128::saturating_add);
+    let max: i128 = bounds.iter().map(|b| b.1).fold(0, i128::saturating_add);
+
+    match constraint.relation {
+        Relation::Eq if min > 0 || max < 0 => return None,
+        Relation::Le if min > 0 => return None,
+        Relation::Ne if min == 0 && max == 0 => return None,
+        _ => (),
+    }
+
+    let mut changed = false;
+    for ((monomial, c), (term_min, term_max)) in constraint.expr.terms.iter().zip(&bounds) {
+        if monomial.len() != 1 {
+            continue;
+        }
+        let var = monomial[0].0;
+        let rest_min = min - term_min;
+        let rest_max = max - term_max;
+        let c = *c as i128;
+        let (lo, hi) = (domains[var].0 as i128, domains[var].1 as i128);
+        let (new_lo, new_hi) = match constraint.relation {
+            // c * x in [-rest_max, -rest_min]
+            Relation::Eq => div_range(-rest_max, -rest_min, c),
+            // c * x <= -rest_min
+            Relation::Le => div_range(i128::MIN / 2, -rest_min, c),
+            // c * x != -rest, only useful when the rest is fixed
+            Relation::Ne => {
+                if rest_min != rest_max || (-rest_min) % c != 0 {
+                    continue;
+                }
+                let excluded = -rest_min / c;
+                match (excluded == lo, excluded == hi) {
+                    (true, _) => (lo + 1, hi),
+                    (_, true) => (lo, hi - 1),
+                    _ => continue,
+                }
+            }
+        };
+        let new_lo = new_lo.max(lo);
+        let new_hi = new_hi.min(hi);
+        if new_lo > new_hi {
+            return None;
+        }
+        if new_lo != lo || new_hi != hi {
+            domains[var] = (new_lo as i64, new_hi as i64);
+            changed = true;
+        }
+    }
+    Some(changed)
+}
+
+// The range of `x` such that `c * x` lies in `[min, max]`.
+fn div_range(min: i128, max: i128, c: i128) -> (i128, i128) {
+    if c > 0 {
+        (div_ceil(min, c), max.div_euclid(c))
+    } else {
+        (div_ceil(max, c), min.div_euclid(c))
+    }
+}
+
+fn div_ceil(a: i128, b: i128) -> i128 {
+    -((-a).div_euclid(b))
+}
+
+fn term_bounds(monomial: &[Var], c: i64, domains: &Domains) -> (i128, i128) {
+    let (min, max) = monomial
+        .iter()
+        .fold((c as i128, c as i128), |(min, max), v| {
+            let (lo, hi) = (domains[v.0].0 as i128, domains[v.0].1 as i128);
+            let corners = [
+                min.saturating_mul(lo),
+                min.saturating_mul(hi),
+                max.saturating_mul(lo),
+                max.saturating_mul(hi),
+            ];
+            (
+                *corners.iter().min().unwrap(),
+                *corners.iter().max().unwrap(),
+            )
+        });
+    (min, max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expr() {
+        let x = Expr::var(Var(0));
+        let y = Expr::var(Var(1));
+        let e = &(&x * &Expr::constant(3)) + &(&y - &Expr::constant(2));
+        assert_eq!(e.eval(&[4, 5]), Some(15));
+        assert_eq!(e.to_string(), "v1 + 3*v0 - 2");
+        assert_eq!((&e - &e).as_constant(), Some(0));
+        assert_eq!((&x * &y).eval(&[6, 7]), Some(42));
+        assert_eq!((&x * &y).eval(&[i64::MAX, 2]), None);
+    }
+
+    #[test]
+    fn test_overflow() {
+        let big = Expr::constant(i64::MAX);
+        assert_eq!((&big + &Expr::constant(1)).as_constant(), None);
+        assert_eq!((&big * &big).as_constant(), None);
+        assert_eq!((-&Expr::constant(i64::MIN)).as_constant(), None);
+        assert_eq!((&big - &big).as_constant(), Some(0));
+
+        let mut solver = Solver::new();
+        let x = solver.add_var(0, 10);
+        let e = &(&Expr::var(x) * &big) * &Expr::constant(2);
+        assert!(e.overflow);
+        solver.add(Constraint::ne(&e, &Expr::constant(0)));
+        assert!(!solver.is_feasible());
+        assert_eq!(solver.solve(), None);
+
+        // Overflow only when evaluated is unsatisfiable too.
+        let mut solver = Solver::new();
+        let x = solver.add_var(0, i64::MAX);
+        let y = solver.add_var(2, 2);
+        solver.add(Constraint::ge(
+            &(&Expr::var(x) * &Expr::var(y)),
+            &Expr::constant(0),
+        ));
+        solver.add(Constraint::ge(&Expr::var(x), &Expr::constant(i64::MAX)));
+        assert_eq!(solver.solve(), None);
+    }
+
+    #[test]
+    fn test_linear_equation() {
+        let mut solver = Solver::new();
+        let noun = solver.add_var(0, 99);
+        let verb = solver.add_var(0, 99);
+        let e = &(&Expr::var(noun) * &Expr::constant(250000)) + &Expr::var(verb);
+        solver.add(Constraint::eq(
+            &(&e + &Expr::constant(190720)),
+            &Expr::constant(19690720),
+        ));
+        assert_eq!(solver.solve_all(10), vec![vec![78, 0]]);
+    }
+
+    #[test]
+    fn test_inequalities() {
+        let mut solver = Solver::new();
+        let x = solver.add_var(-10, 10);
+        solver.add(Constraint::lt(&Expr::var(x), &Expr::constant(3)));
+        solver.add(Constraint::ge(&Expr::var(x), &Expr::constant(1)));
+        solver.add(Constraint::ne(&Expr::var(x), &Expr::constant(2)));
+        assert_eq!(solver.solve_all(10), vec![vec![1]]);
+
+        solver.add(Constraint::eq(&Expr::var(x), &Expr::constant(2)));
+        assert!(!solver.is_feasible());
+    }
+
+    #[test]
+    fn test_nonlinear() {
+        let mut solver = Solver::new();
+        let x = solver.add_var(0, 20);
+        let y = solver.add_var(0, 20);
+        let product = &Expr::var(x) * &Expr::var(y);
+        solver.add(Constraint::eq(&product, &Expr::constant(36)));
+        solver.add(Constraint::lt(&Expr::var(x), &Expr::var(y)));
+        assert_eq!(
+            solver.solve_all(10),
+            vec![vec![2, 18], vec![3, 12], vec![4, 9]]
+        );
+    }
+}
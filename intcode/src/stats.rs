@@ -0,0 +1,119 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// What a machine did while it ran, collected when enabled with
+/// `Machine::collect_stats` or by setting `Computer::stats`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+    /// Instructions executed, by opcode.
+    pub instructions: BTreeMap<i64, u64>,
+    /// The highest address fetched, read or written.
+    pub max_address: usize,
+    pub max_base: i64,
+    pub inputs: u64,
+    pub outputs: u64,
+    /// Time spent inside `run`.
+    pub elapsed: Duration,
+}
+
+impl Stats {
+    pub fn total(&self) -> u64 {
+        self.instructions.values().sum()
+    }
+
+    pub(crate) fn executed(&mut self, opcode: i64) {
+        *self.instructions.entry(opcode).or_insert(0) += 1;
+    }
+
+    pub(crate) fn touched(&mut self, address: usize) {
+        self.max_address = self.max_address.max(address);
+    }
+
+    /// Folds in the statistics of another run, e.g. another machine taking
+    /// part in the same puzzle.
+    pub fn merge(&mut self, other: &Stats) {
+        for (opcode, count) in &other.instructions {
+            *self.instructions.entry(*opcode).or_insert(0) += count;
+        }
+        self.max_address = self.max_address.max(other.max_address);
+        self.max_base = self.max_base.max(other.max_base);
+        self.inputs += other.inputs;
+        self.outputs += other.outputs;
+        self.elapsed += other.elapsed;
+    }
+
+    pub fn to_json(&self) -> String {
+        let instructions: Vec<String> = self
+            .instructions
+            .iter()
+            .map(|(opcode, count)| format!("\"{}\": {}", name(*opcode), count))
+            .collect();
+        format!(
+            "{{\"instructions\": {{{}}}, \"total_instructions\": {}, \"max_address\": {}, \
+             \"max_relative_base\": {}, \"inputs_consumed\": {}, \"outputs_produced\": {}, \
+             \"elapsed_ms\": {:.3}}}",
+            instructions.join(", "),
+            self.total(),
+            self.max_address,
+            self.max_base,
+            self.inputs,
+            self.outputs,
+            self.elapsed.as_secs_f64() * 1000.0
+        )
+    }
+}
+
+fn name(opcode: i64) -> String {
+    match opcode {
+        1 => "add".to_string(),
+        2 => "multiply".to_string(),
+        3 => "input".to_string(),
+        4 => "output".to_string(),
+        5 => "jump_if_true".to_string(),
+        6 => "jump_if_false".to_string(),
+        7 => "less_than".to_string(),
+        8 => "equals".to_string(),
+        9 => "adjust_base".to_string(),
+        99 => "halt".to_string(),
+        opcode => opcode.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::machine::Engine;
+
+    #[test]
+    fn test_stats() {
+        // base += 5; [base + 95] = input; output it twice
+        let program = [109, 5, 203, 95, 204, 95, 204, 95, 99];
+        let mut all = Stats::default();
+        for engine in Engine::ALL.iter() {
+            let mut m = engine.machine(&program);
+            m.collect_stats();
+            m.push_input(7);
+            m.run().unwrap();
+            let mut stats = m.stats().unwrap().clone();
+            assert!(stats.elapsed > Duration::default());
+            stats.elapsed = Duration::default();
+            let expected = Stats {
+                instructions: vec![(3, 1), (4, 2), (9, 1), (99, 1)].into_iter().collect(),
+                max_address: 100,
+                max_base: 5,
+                inputs: 1,
+                outputs: 2,
+                elapsed: Duration::default(),
+            };
+            assert_eq!(stats, expected, "{}", engine);
+            all.merge(&stats);
+        }
+        assert_eq!(all.total(), 20);
+        assert_eq!(
+            all.to_json(),
+            "{\"instructions\": {\"input\": 4, \"output\": 8, \"adjust_base\": 4, \"halt\": 4}, \
+             \"total_instructions\": 20, \"max_address\": 100, \"max_relative_base\": 5, \
+             \"inputs_consumed\": 4, \"outputs_produced\": 8, \"elapsed_ms\": 0.000}"
+        );
+    }
+}
@@ -0,0 +1,574 @@
+use std::collections::VecDeque;
+use std::error;
+use std::fmt;
+use std::ops::{Index, RangeInclusive};
+
+use crate::machine::Machine;
+use crate::solver::{Constraint, Expr, Solver, Var};
+use crate::{Computer, Status};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Expr(Expr),
+    /// Loaded from an address that depends on a symbolic variable. It can be
+    /// copied and overwritten but not branched on or solved for.
+    Unknown,
+}
+
+impl Value {
+    fn constant(value: i64) -> Value {
+        Value::Expr(Expr::constant(value))
+    }
+
+    fn as_constant(&self) -> Option<i64> {
+        match self {
+            Value::Expr(e) => e.as_constant(),
+            Value::Unknown => None,
+        }
+    }
+
+    fn combine(&self, other: &Value, f: impl Fn(&Expr, &Expr) -> Expr) -> Value {
+        match (self, other) {
+            (Value::Expr(x), Value::Expr(y)) => Value::Expr(f(x, y)),
+            _ => Value::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Expr(e) => write!(f, "{}", e),
+            Value::Unknown => write!(f, "?"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    UnknownOpcode { opcode: i64, ip: i64 },
+    UnknownMode { mode: u8, ip: i64 },
+    InvalidAddress { address: i64, ip: i64 },
+    SymbolicInstruction { ip: i64 },
+    SymbolicAddress { ip: i64 },
+    UnknownValue { ip: i64 },
+    StepLimit { ip: i64 },
+    PathLimit,
+    UnknownTarget,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::UnknownOpcode { opcode, ip } => {
+                write!(f, "Unrecognised opcode: {}, ip={}", opcode, ip)
+            }
+            Error::UnknownMode { mode, ip } => write!(f, "Unknown mode: {}, ip={}", mode, ip),
+            Error::InvalidAddress { address, ip } => {
+                write!(f, "Invalid address: {}, ip={}", address, ip)
+            }
+            Error::SymbolicInstruction { ip } => write!(f, "Symbolic instruction, ip={}", ip),
+            Error::SymbolicAddress { ip } => write!(f, "Symbolic address, ip={}", ip),
+            Error::UnknownValue { ip } => {
+                write!(
+                    f,
+                    "Branch on a value loaded from a symbolic address, ip={}",
+                    ip
+                )
+            }
+            Error::StepLimit { ip } => write!(f, "Step limit reached, ip={}", ip),
+            Error::PathLimit => write!(f, "Path limit reached"),
+            Error::UnknownTarget => write!(f, "Target was loaded from a symbolic address"),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+/// Where to look for the value being solved for once a path halts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Memory(usize),
+    Output(usize),
+}
+
+/// One feasible route through the program, with the constraints on the
+/// symbolic variables that lead down it.
+#[derive(Debug, Clone)]
+pub struct Path {
+    pub status: Status,
+    pub memory: Vec<Value>,
+    pub output: Vec<Value>,
+    pub constraints: Vec<Constraint>,
+}
+
+impl Path {
+    pub fn get(&self, target: Target) -> Option<Value> {
+        match target {
+            Target::Memory(address) => Some(
+                self.memory
+                    .get(address)
+                    .cloned()
+                    .unwrap_or_else(|| Value::constant(0)),
+            ),
+            Target::Output(index) => self.output.get(index).cloned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Solution {
+    values: Vec<i64>,
+}
+
+impl Index<Var> for Solution {
+    type Output = i64;
+
+    fn index(&self, var: Var) -> &i64 {
+        &self.values[var.0]
+    }
+}
+
+#[derive(Clone)]
+struct State {
+    ip: i64,
+    base: i64,
+    memory: Vec<Value>,
+    input: VecDeque<Value>,
+    output: Vec<Value>,
+    constraints: Vec<Constraint>,
+    // The outcome already chosen for a symbolic condition at `ip`.
+    decision: Option<bool>,
+    steps: usize,
+}
+
+enum Step {
+    Continue,
+    Branch(Constraint, Constraint),
+    Stop(Status),
+}
+
+/// Runs an Intcode program with some memory cells and inputs replaced by
+/// bounded symbolic variables, forking at every branch whose outcome depends
+/// on them.
+pub struct Executor {
+    program: Vec<i64>,
+    solver: Solver,
+    cells: Vec<(usize, Var)>,
+    input: Vec<Value>,
+    pub max_paths: usize,
+    pub max_steps: usize,
+    pub max_solutions: usize,
+}
+
+impl Executor {
+    pub fn new(program: &[i64]) -> Executor {
+        Executor {
+            program: program.to_vec(),
+            solver: Solver::new(),
+            cells: Vec::new(),
+            input: Vec::new(),
+            max_paths: 1000,
+            max_steps: 1_000_000,
+            max_solutions: 1000,
+        }
+    }
+
+    pub fn symbolic_cell(&mut self, address: usize, range: RangeInclusive<i64>) -> Var {
+        let var = self.solver.add_var(*range.start(), *range.end());
+        self.cells.push((address, var));
+        var
+    }
+
+    pub fn symbolic_input(&mut self, range: RangeInclusive<i64>) -> Var {
+        let var = self.solver.add_var(*range.start(), *range.end());
+        self.input.push(Value::Expr(Expr::var(var)));
+        var
+    }
+
+    pub fn push_input(&mut self, value: i64) {
+        self.input.push(Value::constant(value));
+    }
+
+    /// Builds a concrete `Computer` with the symbolic cells and inputs set to
+    /// the values in `solution`, e.g. to check it against the interpreter.
+    pub fn computer(&self, solution: &Solution) -> Computer {
+        let mut cpu = Computer::new(self.program.clone());
+        for (address, var) in &self.cells {
+            Machine::write(&mut cpu, *address, solution[*var]);
+        }
+        for value in &self.input {
+            if let Value::Expr(e) = value {
+                // Inputs are constants or single variables, so always fit.
+                cpu.push_input(e.eval(&solution.values).unwrap_or_default());
+            }
+        }
+        cpu
+    }
+
+    pub fn explore(&self) -> Result<Vec<Path>, Error> {
+        let mut memory: Vec<Value> = self.program.iter().map(|v| Value::constant(*v)).collect();
+        for (address, var) in &self.cells {
+            if *address >= memory.len() {
+                memory.resize(address + 1, Value::constant(0));
+            }
+            memory[*address] = Value::Expr(Expr::var(*var));
+        }
+        let mut pending = vec![State {
+            ip: 0,
+            base: 0,
+            memory,
+            input: self.input.iter().cloned().collect(),
+            output: Vec::new(),
+            constraints: Vec::new(),
+            decision: None,
+            steps: 0,
+        }];
+        let mut paths = Vec::new();
+
+        while let Some(mut state) = pending.pop() {
+            loop {
+                state.steps += 1;
+                if state.steps > self.max_steps {
+                    return Err(Error::StepLimit { ip: state.ip });
+                }
+                match self.step(&mut state)? {
+                    Step::Continue => (),
+                    Step::Branch(when_true, when_false) => {
+                        let taken = self.is_feasible(&state.constraints, &when_true);
+                        let not_taken = self.is_feasible(&state.constraints, &when_false);
+                        match (taken, not_taken) {
+                            (true, true) => {
+                                if paths.len() + pending.len() + 2 > self.max_paths {
+                                    return Err(Error::PathLimit);
+                                }
+                                let mut other = state.clone();
+                                other.constraints.push(when_false);
+                                other.decision = Some(false);
+                                pending.push(other);
+                                state.constraints.push(when_true);
+                                state.decision = Some(true);
+                            }
+                            (true, false) => {
+                                state.constraints.push(when_true);
+                                state.decision = Some(true);
+                            }
+                            (false, true) => {
+                                state.constraints.push(when_false);
+                                state.decision = Some(false);
+                            }
+                            (false, false) => break,
+                        }
+                    }
+                    Step::Stop(status) => {
+                        paths.push(Path {
+                            status,
+                            memory: state.memory,
+                            output: state.output,
+                            constraints: state.constraints,
+                        });
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(paths)
+    }
+
+    /// Finds assignments of the symbolic variables for which `target` equals
+    /// `goal` when the program halts.
+    pub fn solve(&self, target: Target, goal: i64) -> Result<Vec<Solution>, Error> {
+        let mut solutions = Vec::new();
+        for path in self.explore()? {
+            if path.status != Status::Halted {
+                continue;
+            }
+            let value = match path.get(target) {
+                Some(Value::Expr(e)) => e,
+                Some(Value::Unknown) => return Err(Error::UnknownTarget),
+                None => continue,
+            };
+            let mut solver = self.solver.clone();
+            for constraint in path.constraints {
+                solver.add(constraint);
+            }
+            solver.add(Constraint::eq(&value, &Expr::constant(goal)));
+            let limit = self.max_solutions - solutions.len();
+            solutions.extend(
+                solver
+                    .solve_all(limit)
+                    .into_iter()
+                    .map(|values| Solution { values }),
+            );
+            if solutions.len() >= self.max_solutions {
+                break;
+            }
+        }
+        solutions.sort();
+        Ok(solutions)
+    }
+
+    fn is_feasible(&self, constraints: &[Constraint], extra: &Constraint) -> bool {
+        let mut solver = self.solver.clone();
+        for constraint in constraints.iter().chain(Some(extra)) {
+            solver.add(constraint.clone());
+        }
+        solver.is_feasible()
+    }
+
+    fn step(&self, state: &mut State) -> Result<Step, Error> {
+        let ip = state.ip;
+        let instruction = load(state, ip, ip)?
+            .as_constant()
+            .ok_or(Error::SymbolicInstruction { ip })?;
+        let (opcode, modes) = Computer::decode(instruction as i32);
+        match opcode {
+            1 | 2 => {
+                // add, multiply
+                let x = self.read(state, 1, modes[0])?;
+                let y = self.read(state, 2, modes[1])?;
+                let value = if opcode == 1 {
+                    x.combine(&y, |x, y| x + y)
+                } else {
+                    x.combine(&y, |x, y| x * y)
+                };
+                self.write(state, 3, modes[2], value)?;
+                state.ip += 4;
+            }
+            3 => {
+                // input
+                let value = match state.input.pop_front() {
+                    Some(value) => value,
+                    None => return Ok(Step::Stop(Status::AwaitingInput)),
+                };
+                self.write(state, 1, modes[0], value)?;
+                state.ip += 2;
+            }
+            4 => {
+                // output
+                let value = self.read(state, 1, modes[0])?;
+                state.output.push(value);
+                state.ip += 2;
+            }
+            5 | 6 => {
+                // jump if true, jump if false
+                let x = self.expr(state, 1, modes[0])?;
+                let zero = Expr::constant(0);
+                let jump = match decide(state, Constraint::ne(&x, &zero), Constraint::eq(&x, &zero))
+                {
+                    Ok(truth) => truth == (opcode == 5),
+                    Err(branch) => return Ok(branch),
+                };
+                if jump {
+                    state.ip = self
+                        .read(state, 2, modes[1])?
+                        .as_constant()
+                        .ok_or(Error::SymbolicAddress { ip })?;
+                } else {
+                    state.ip += 3;
+                }
+            }
+            7 | 8 => {
+                // set less than, set equal to
+                let x = self.expr(state, 1, modes[0])?;
+                let y = self.expr(state, 2, modes[1])?;
+                let (when_true, when_false) = if opcode == 7 {
+                    (Constraint::lt(&x, &y), Constraint::ge(&x, &y))
+                } else {
+                    (Constraint::eq(&x, &y), Constraint::ne(&x, &y))
+                };
+                let truth = match decide(state, when_true, when_false) {
+                    Ok(truth) => truth,
+                    Err(branch) => return Ok(branch),
+                };
+                self.write(state, 3, modes[2], Value::constant(truth as i64))?;
+                state.ip += 4;
+            }
+            9 => {
+                // relative base
+                let value = self
+                    .read(state, 1, modes[0])?
+                    .as_constant()
+                    .ok_or(Error::SymbolicAddress { ip })?;
+                state.base += value;
+                state.ip += 2;
+            }
+            99 => {
+                state.ip += 1;
+                return Ok(Step::Stop(Status::Halted));
+            }
+            opcode => {
+                return Err(Error::UnknownOpcode {
+                    opcode: opcode as i64,
+                    ip,
+                })
+            }
+        }
+        Ok(Step::Continue)
+    }
+
+    fn read(&self, state: &State, offset: i64, mode: u8) -> Result<Value, Error> {
+        let ip = state.ip;
+        let param = load(state, ip + offset, ip)?;
+        let address = match mode {
+            0 => param,
+            1 => return Ok(param),
+            2 => param.combine(&Value::constant(state.base), |x, y| x + y),
+            m => return Err(Error::UnknownMode { mode: m, ip }),
+        };
+        match address.as_constant() {
+            Some(address) => load(state, address, ip),
+            None => Ok(Value::Unknown),
+        }
+    }
+
+    fn expr(&self, state: &State, offset: i64, mode: u8) -> Result<Expr, Error> {
+        match self.read(state, offset, mode)? {
+            Value::Expr(e) => Ok(e),
+            Value::Unknown => Err(Error::UnknownValue { ip: state.ip }),
+        }
+    }
+
+    fn write(&self, state: &mut State, offset: i64, mode: u8, value: Value) -> Result<(), Error> {
+        let ip = state.ip;
+        let param = load(state, ip + offset, ip)?
+            .as_constant()
+            .ok_or(Error::SymbolicAddress { ip })?;
+        let address = match mode {
+            0 => param,
+            2 => param + state.base,
+            m => return Err(Error::UnknownMode { mode: m, ip }),
+        };
+        if address < 0 {
+            return Err(Error::InvalidAddress { address, ip });
+        }
+        let address = address as usize;
+        if address >= state.memory.len() {
+            state.memory.resize(address + 1, Value::constant(0));
+        }
+        state.memory[address] = value;
+        Ok(())
+    }
+}
+
+fn load(state: &State, address: i64, ip: i64) -> Result<Value, Error> {
+    if address < 0 {
+        return Err(Error::InvalidAddress { address, ip });
+    }
+    Ok(state
+        .memory
+        .get(address as usize)
+        .cloned()
+        .unwrap_or_else(|| Value::constant(0)))
+}
+
+// Resolves a condition to true or false, either because it is concrete or
+// because the executor has already picked a side; otherwise asks for a branch.
+fn decide(state: &mut State, when_true: Constraint, when_false: Constraint) -> Result<bool, Step> {
+    if when_true.expr.as_constant().is_some() {
+        return Ok(when_true.holds(&[]));
+    }
+    match state.decision.take() {
+        Some(truth) => Ok(truth),
+        None => Err(Step::Branch(when_true, when_false)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_instructions;
+    use std::fs;
+
+    #[test]
+    fn test_linear() {
+        // [0] = ([9] + 3) * [10]
+        let program = vec![1001, 9, 3, 0, 2, 0, 10, 0, 99, 0, 0];
+        let mut exec = Executor::new(&program);
+        let x = exec.symbolic_cell(9, 0..=10);
+        let y = exec.symbolic_cell(10, 0..=10);
+        let paths = exec.explore().unwrap();
+        assert_eq!(paths.len(), 1);
+        assert!(exec.solve(Target::Memory(0), 200).unwrap().is_empty());
+        let solutions = exec.solve(Target::Memory(0), 12).unwrap();
+        let pairs: Vec<(i64, i64)> = solutions.iter().map(|s| (s[x], s[y])).collect();
+        assert_eq!(pairs, vec![(0, 4), (1, 3), (3, 2), (9, 1)]);
+    }
+
+    #[test]
+    fn test_branching_input() {
+        // day05: output 999 below 8, 1000 for 8 and 1001 above
+        let program = vec![
+            3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36, 98, 0,
+            0, 1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000, 1, 20, 4,
+            20, 1105, 1, 46, 98, 99,
+        ];
+        let mut exec = Executor::new(&program);
+        let input = exec.symbolic_input(0..=100);
+        assert_eq!(exec.explore().unwrap().len(), 3);
+        let solutions = exec.solve(Target::Output(0), 1001).unwrap();
+        assert_eq!(solutions.len(), 92);
+        assert_eq!(solutions[0][input], 9);
+        let solutions = exec.solve(Target::Output(0), 1000).unwrap();
+        assert_eq!(solutions.len(), 1);
+        assert_eq!(solutions[0][input], 8);
+    }
+
+    #[test]
+    fn test_awaiting_input() {
+        let mut exec = Executor::new(&[3, 0, 3, 1, 99]);
+        exec.push_input(5);
+        let paths = exec.explore().unwrap();
+        assert_eq!(paths[0].status, Status::AwaitingInput);
+        assert_eq!(paths[0].get(Target::Memory(0)), Some(Value::constant(5)));
+    }
+
+    #[test]
+    fn test_symbolic_jump_target() {
+        let mut exec = Executor::new(&[1105, 1, 0, 99]);
+        exec.symbolic_cell(2, 0..=3);
+        assert_eq!(
+            exec.explore().unwrap_err(),
+            Error::SymbolicAddress { ip: 0 }
+        );
+    }
+
+    #[test]
+    fn test_cell_past_program() {
+        // [0] = [10] + 5
+        let mut exec = Executor::new(&[1001, 10, 5, 0, 99]);
+        let x = exec.symbolic_cell(10, 0..=10);
+        let solutions = exec.solve(Target::Memory(0), 12).unwrap();
+        assert_eq!(solutions.len(), 1);
+        assert_eq!(solutions[0][x], 7);
+
+        let mut cpu = exec.computer(&solutions[0]);
+        assert_eq!(cpu.try_run(), Ok(Status::Halted));
+        assert_eq!(cpu.memory[0], 12);
+    }
+
+    #[test]
+    fn test_overflow() {
+        // [0] = [10] * i64::MAX * 2, which overflows for any positive [10]
+        let mut exec = Executor::new(&[1002, 10, i64::MAX, 11, 1002, 11, 2, 0, 99]);
+        exec.symbolic_cell(10, 1..=10);
+        assert!(exec.solve(Target::Memory(0), 0).unwrap().is_empty());
+        assert!(exec.solve(Target::Memory(0), -2).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_day02() {
+        let input = fs::read_to_string("../day02/data/input.txt").expect("Error reading file");
+        let instructions = parse_instructions(&input).unwrap();
+        let mut exec = Executor::new(&instructions);
+        let noun = exec.symbolic_cell(1, 0..=99);
+        let verb = exec.symbolic_cell(2, 0..=99);
+        let solutions = exec.solve(Target::Memory(0), 19690720).unwrap();
+        assert_eq!(solutions.len(), 1);
+        assert_eq!((solutions[0][noun], solutions[0][verb]), (56, 96));
+
+        let mut cpu = exec.computer(&solutions[0]);
+        cpu.run();
+        assert_eq!(cpu.memory[0], 19690720);
+    }
+}
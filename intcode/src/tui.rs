@@ -0,0 +1,368 @@
+use std::convert::TryFrom;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+use crate::disasm::{disassemble, disassemble_from, Item};
+use crate::{Computer, Status};
+
+const PANEL_WIDTH: usize = 40;
+const FRAME: Duration = Duration::from_millis(50);
+const KEYS: &str = "space pause/resume  s step  + faster  - slower  q quit";
+
+/// A live terminal view of one or more machines, one panel each, drawn with
+/// ANSI escape codes. Each panel shows the registers, the disassembly around
+/// the instruction pointer, the I/O queues and memory around the relative
+/// base. Pipes carry one machine's output to another's input, which is all
+/// a day07-style amplifier chain needs.
+pub struct Dashboard {
+    panels: Vec<Panel>,
+    pipes: Vec<(usize, usize)>,
+    pub paused: bool,
+    /// Instructions per machine per frame while running.
+    pub speed: usize,
+}
+
+struct Panel {
+    name: String,
+    cpu: Computer,
+    status: Option<Status>,
+    error: Option<String>,
+    // Output already passed down a pipe, most recent last
+    sent: Vec<i64>,
+}
+
+impl Default for Dashboard {
+    fn default() -> Dashboard {
+        Dashboard {
+            panels: Vec::new(),
+            pipes: Vec::new(),
+            paused: true,
+            speed: 1,
+        }
+    }
+}
+
+impl Dashboard {
+    pub fn new() -> Dashboard {
+        Dashboard::default()
+    }
+
+    /// Adds a panel for a machine, returning its index for `connect`.
+    pub fn add(&mut self, name: &str, cpu: Computer) -> usize {
+        self.panels.push(Panel {
+            name: name.to_string(),
+            cpu,
+            status: None,
+            error: None,
+            sent: Vec::new(),
+        });
+        self.panels.len() - 1
+    }
+
+    /// Feeds everything machine `from` outputs to machine `to` as input.
+    pub fn connect(&mut self, from: usize, to: usize) {
+        self.pipes.push((from, to));
+    }
+
+    pub fn computer(&self, panel: usize) -> &Computer {
+        &self.panels[panel].cpu
+    }
+
+    /// Everything a machine has output, including what went down a pipe.
+    pub fn output(&self, panel: usize) -> Vec<i64> {
+        let panel = &self.panels[panel];
+        panel
+            .sent
+            .iter()
+            .chain(&panel.cpu.output)
+            .cloned()
+            .collect()
+    }
+
+    /// Executes one instruction on every machine that can go on, then
+    /// passes output along the pipes. Returns false once none of them can.
+    pub fn step(&mut self) -> bool {
+        let mut progress = false;
+        for panel in self.panels.iter_mut() {
+            let blocked = panel.status == Some(Status::AwaitingInput) && panel.cpu.input.is_empty();
+            if panel.cpu.halted || panel.error.is_some() || blocked {
+                continue;
+            }
+            match panel.cpu.try_step() {
+                Ok(status) => {
+                    progress |= status.is_none();
+                    panel.status = status;
+                }
+                Err(e) => panel.error = Some(panel.cpu.explain(&e)),
+            }
+        }
+        for &(from, to) in &self.pipes {
+            let values: Vec<i64> = self.panels[from].cpu.output.drain(..).collect();
+            for value in values {
+                self.panels[from].sent.push(value);
+                self.panels[to].cpu.push_input(value);
+            }
+        }
+        progress
+    }
+
+    /// Draws every panel, side by side in rows that fit in `width` columns.
+    pub fn render(&self, width: usize) -> String {
+        let per_row = (width / (PANEL_WIDTH + 2)).max(1);
+        let mut text = String::new();
+        for row in self.panels.chunks(per_row) {
+            let columns: Vec<Vec<String>> = row.iter().map(Panel::lines).collect();
+            let height = columns.iter().map(Vec::len).max().unwrap_or(0);
+            for i in 0..height {
+                let line: Vec<String> = columns
+                    .iter()
+                    .map(|c| {
+                        let cell = c.get(i).map_or("", String::as_str);
+                        let visible = visible_len(cell);
+                        if visible > PANEL_WIDTH {
+                            cell.chars().take(PANEL_WIDTH).collect()
+                        } else {
+                            format!("{}{}", cell, " ".repeat(PANEL_WIDTH - visible))
+                        }
+                    })
+                    .collect();
+                text += line.join("  ").trim_end();
+                text += "\n";
+            }
+            text += "\n";
+        }
+        text
+    }
+
+    /// Runs the dashboard on the terminal until every machine stops and the
+    /// user quits, with keys to pause, single-step and change speed. Keys
+    /// come from `/dev/tty`, so stdin is still free for puzzle input.
+    pub fn run(&mut self) -> io::Result<()> {
+        let mut tty = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+        let saved = stty(&tty, &["-g"])?;
+        stty(&tty, &["-icanon", "-echo", "min", "0", "time", "0"])?;
+        let result = self.event_loop(&mut tty);
+        stty(&tty, &[saved.trim()])?;
+        write!(tty, "\x1b[?25h")?;
+        result
+    }
+
+    fn event_loop(&mut self, tty: &mut File) -> io::Result<()> {
+        let width = std::env::var("COLUMNS")
+            .ok()
+            .and_then(|c| c.parse().ok())
+            .unwrap_or(120);
+        let mut done = false;
+        loop {
+            let mut key = [0u8; 1];
+            while tty.read(&mut key)? == 1 {
+                match key[0] {
+                    b'q' => return Ok(()),
+                    b' ' => self.paused = !self.paused,
+                    b's' => {
+                        self.paused = true;
+                        done = !self.step();
+                    }
+                    b'+' => self.speed *= 2,
+                    b'-' => self.speed = (self.speed / 2).max(1),
+                    _ => (),
+                }
+            }
+            if !self.paused && !done {
+                for _ in 0..self.speed {
+                    if !self.step() {
+                        done = true;
+                        break;
+                    }
+                }
+            }
+            let state = if done {
+                "stopped"
+            } else if self.paused {
+                "paused"
+            } else {
+                "running"
+            };
+            writeln!(
+                tty,
+                "\x1b[?25l\x1b[H\x1b[2J{}{} x{}  {}",
+                self.render(width),
+                state,
+                self.speed,
+                KEYS
+            )?;
+            tty.flush()?;
+            thread::sleep(FRAME);
+        }
+    }
+}
+
+impl Panel {
+    fn lines(&self) -> Vec<String> {
+        let cpu = &self.cpu;
+        let state = match (&self.error, cpu.halted, self.status) {
+            (Some(_), _, _) => "error",
+            (_, true, _) => "halted",
+            (_, _, Some(Status::AwaitingInput)) if cpu.input.is_empty() => "awaiting input",
+            _ => "ready",
+        };
+        let mut lines = vec![
+            format!("\x1b[1m{}\x1b[0m  {}", self.name, state),
+            format!("ip={} base={} steps={}", cpu.ip, cpu.base, cpu.steps),
+        ];
+        if let Some(error) = &self.error {
+            lines.push(error.clone());
+        }
+
+        lines.push("-- code".to_string());
+        let memory = cpu.memory.to_vec();
+        let ip = cpu.ip.max(0) as usize;
+        let before = disassemble(&memory[..ip.min(memory.len())]);
+        let before = match before.last() {
+            Some(last) if last.address() + last.size() == ip => {
+                &before[before.len().saturating_sub(3)..]
+            }
+            _ => &[],
+        };
+        for item in before {
+            lines.push(format!("  {:>5}  {}", item.address(), item));
+        }
+        for (i, item) in disassemble_from(&memory, ip, 5).iter().enumerate() {
+            let marker = if i == 0 { ">" } else { " " };
+            lines.push(format!(
+                "{} {:>5}  {}",
+                marker,
+                item.address(),
+                describe(item, cpu)
+            ));
+        }
+
+        lines.push(format!("in:  {:?}", tail(&cpu.input, 8)));
+        let output: Vec<i64> = self.sent.iter().chain(&cpu.output).cloned().collect();
+        lines.push(format!("out: {:?}", tail(&output, 8)));
+
+        lines.push("-- stack".to_string());
+        for offset in -3..=3 {
+            let address = cpu.base + offset;
+            if let Some(value) = usize::try_from(address)
+                .ok()
+                .and_then(|a| cpu.memory.get(a))
+            {
+                let marker = if offset == 0 { ">" } else { " " };
+                lines.push(format!(
+                    "{} rb{:+}  {:>5}: {}",
+                    marker, offset, address, value
+                ));
+            }
+        }
+        lines
+    }
+}
+
+// An instruction with the current values of its position and relative
+// operands, e.g. `add 30, #5, 30  [30]=7`
+fn describe(item: &Item, cpu: &Computer) -> String {
+    let mut text = item.to_string();
+    if let Item::Instruction { modes, params, .. } = item {
+        let mut shown = Vec::new();
+        for (mode, param) in modes.iter().zip(params) {
+            let address = match mode {
+                0 => *param,
+                2 => cpu.base + param,
+                _ => continue,
+            };
+            if shown.contains(&address) {
+                continue;
+            }
+            shown.push(address);
+            if let Some(value) = usize::try_from(address)
+                .ok()
+                .and_then(|a| cpu.memory.get(a))
+            {
+                text += &format!("  [{}]={}", address, value);
+            }
+        }
+    }
+    text
+}
+
+// Length on screen, skipping escape sequences
+fn visible_len(s: &str) -> usize {
+    let mut len = 0;
+    let mut escape = false;
+    for c in s.chars() {
+        match c {
+            '\x1b' => escape = true,
+            'm' if escape => escape = false,
+            _ if !escape => len += 1,
+            _ => (),
+        }
+    }
+    len
+}
+
+fn tail(values: &[i64], n: usize) -> &[i64] {
+    &values[values.len().saturating_sub(n)..]
+}
+
+fn stty(tty: &File, args: &[&str]) -> io::Result<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(tty.try_clone()?)
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble;
+
+    #[test]
+    fn test_pipes() {
+        // Each machine adds one to its input and passes it on
+        let program = assemble(
+            "inc.asm",
+            "loop: in x\nadd x, #1, x\nout x\njt #1, #loop\nx: data 0",
+        )
+        .unwrap()
+        .program;
+        let mut dashboard = Dashboard::new();
+        let a = dashboard.add("amp A", Computer::new(program.clone()));
+        let b = dashboard.add("amp B", Computer::new(program));
+        dashboard.connect(a, b);
+        dashboard.panels[a].cpu.push_input(10);
+        while dashboard.step() {}
+        assert_eq!(dashboard.output(a), vec![11]);
+        assert_eq!(dashboard.output(b), vec![12]);
+        assert!(dashboard.computer(a).input.is_empty());
+
+        let text = dashboard.render(90);
+        let first = text.lines().next().unwrap();
+        assert!(first.starts_with("\x1b[1mamp A\x1b[0m  awaiting input"));
+        assert!(first.contains("amp B\x1b[0m  awaiting input"));
+        assert!(text.contains(">     0  in 11  [11]=11"));
+        assert!(text.contains("      2  add 11, #1, 11  [11]=11 "));
+        assert!(text.contains("out: [11]"));
+        assert!(text.contains("out: [12]"));
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut dashboard = Dashboard::new();
+        dashboard.add("bad", Computer::new(vec![1, 0, 0, 0, 42]));
+        assert!(dashboard.step());
+        assert!(!dashboard.step());
+        let text = dashboard.render(40);
+        assert!(text.contains("bad\x1b[0m  error"));
+        assert!(text.contains("Unrecognised opcode: 42, ip=4"));
+    }
+}
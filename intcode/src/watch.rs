@@ -0,0 +1,284 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Exec,
+    Read,
+    Write,
+}
+
+/// What a stretch of memory is used for. Addresses are classified from how
+/// they were touched unless a label says otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Region {
+    /// Fetched as an opcode or parameter.
+    Code,
+    /// Read or written through a relative-mode parameter.
+    Stack,
+    /// Read or written through a position-mode parameter.
+    Globals,
+}
+
+impl Region {
+    fn colour(self) -> [u8; 3] {
+        match self {
+            Region::Code => [24, 24, 72],
+            Region::Stack => [24, 64, 24],
+            Region::Globals => [56, 56, 56],
+        }
+    }
+}
+
+/// Access counts for one slice of the run, keyed by address, as
+/// `[exec, read, write]`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Frame {
+    pub step: u64,
+    pub counts: BTreeMap<usize, [u32; 3]>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub name: String,
+    pub range: Range<usize>,
+    pub region: Region,
+}
+
+/// Records memory traffic for a `Computer`, split into frames of a fixed
+/// number of instructions.
+#[derive(Debug, Clone)]
+pub struct Watch {
+    pub frame_steps: u64,
+    frames: Vec<Frame>,
+    regions: BTreeMap<usize, Region>,
+    labels: Vec<Label>,
+}
+
+impl Watch {
+    pub fn new(frame_steps: u64) -> Watch {
+        Watch {
+            frame_steps: frame_steps.max(1),
+            frames: vec![Frame::default()],
+            regions: BTreeMap::new(),
+            labels: Vec::new(),
+        }
+    }
+
+    pub fn label(&mut self, name: &str, range: Range<usize>, region: Region) {
+        self.labels.push(Label {
+            name: name.to_string(),
+            range,
+            region,
+        });
+    }
+
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    pub(crate) fn record(&mut self, address: usize, access: Access, mode: u8) {
+        let index = match access {
+            Access::Exec => 0,
+            Access::Read => 1,
+            Access::Write => 2,
+        };
+        self.frames
+            .last_mut()
+            .unwrap()
+            .counts
+            .entry(address)
+            .or_default()[index] += 1;
+
+        let region = match (access, mode) {
+            (Access::Exec, _) => Region::Code,
+            (_, 2) => Region::Stack,
+            _ => Region::Globals,
+        };
+        let current = self.regions.entry(address).or_insert(region);
+        *current = (*current).min(region);
+    }
+
+    pub(crate) fn tick(&mut self, steps: u64) {
+        if steps.is_multiple_of(self.frame_steps) {
+            self.frames.push(Frame {
+                step: steps,
+                counts: BTreeMap::new(),
+            });
+        }
+    }
+
+    pub fn region(&self, address: usize) -> Option<Region> {
+        self.labels
+            .iter()
+            .rev()
+            .find(|l| l.range.contains(&address))
+            .map(|l| l.region)
+            .or_else(|| self.regions.get(&address).cloned())
+    }
+
+    /// Contiguous runs of addresses in the same region, with the name of the
+    /// label covering them if there is one.
+    pub fn regions(&self) -> Vec<(Range<usize>, Region, Option<String>)> {
+        let mut addresses: Vec<usize> = self.regions.keys().cloned().collect();
+        for label in &self.labels {
+            addresses.extend(label.range.clone());
+        }
+        addresses.sort_unstable();
+        addresses.dedup();
+
+        let name = |a: usize| {
+            self.labels
+                .iter()
+                .rev()
+                .find(|l| l.range.contains(&a))
+                .map(|l| l.name.clone())
+        };
+        let mut result: Vec<(Range<usize>, Region, Option<String>)> = Vec::new();
+        for a in addresses {
+            let region = self.region(a).unwrap();
+            let label = name(a);
+            match result.last_mut() {
+                Some((range, r, l)) if range.end == a && *r == region && *l == label => {
+                    range.end += 1;
+                }
+                _ => result.push((a..a + 1, region, label)),
+            }
+        }
+        result
+    }
+
+    pub fn legend(&self) -> String {
+        self.regions()
+            .iter()
+            .map(|(range, region, label)| {
+                let label = label
+                    .as_ref()
+                    .map(|l| format!(" {}", l))
+                    .unwrap_or_default();
+                format!(
+                    "{:>6}..{:<6} {:?}{}\n",
+                    range.start, range.end, region, label
+                )
+            })
+            .collect()
+    }
+
+    /// Renders a frame as a binary PPM, one `scale`-pixel square per address
+    /// and `width` addresses per row. The background shows the region, and
+    /// instruction fetches, reads and writes light up the blue, green and red
+    /// channels in proportion to the busiest address in the frame.
+    pub fn render_ppm(&self, frame: usize, width: usize, scale: usize) -> Vec<u8> {
+        let len = self
+            .regions
+            .keys()
+            .next_back()
+            .map(|a| a + 1)
+            .unwrap_or(0)
+            .max(self.labels.iter().map(|l| l.range.end).max().unwrap_or(0));
+        let width = width.max(1);
+        let rows = len.div_ceil(width).max(1);
+        let counts = &self.frames[frame].counts;
+        let max = counts.values().flatten().cloned().max().unwrap_or(0).max(1);
+
+        let (w, h) = (width * scale, rows * scale);
+        let mut ppm = format!("P6\n{} {}\n255\n", w, h).into_bytes();
+        for y in 0..h {
+            for x in 0..w {
+                let address = (y / scale) * width + x / scale;
+                let mut pixel = self.region(address).map(Region::colour).unwrap_or([0; 3]);
+                if let Some(c) = counts.get(&address) {
+                    let heat = |n: u32| (n as u64 * 255 / max as u64) as u8;
+                    pixel[2] = pixel[2].max(heat(c[0]));
+                    pixel[1] = pixel[1].max(heat(c[1]));
+                    pixel[0] = pixel[0].max(heat(c[2]));
+                }
+                ppm.extend_from_slice(&pixel);
+            }
+        }
+        ppm
+    }
+
+    /// Writes every frame to `dir` as `frame-NNNNN.ppm`.
+    pub fn export(&self, dir: &Path, width: usize, scale: usize) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        for i in 0..self.frames.len() {
+            let mut file = fs::File::create(dir.join(format!("frame-{:05}.ppm", i)))?;
+            file.write_all(&self.render_ppm(i, width, scale))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Computer;
+
+    fn watch(program: Vec<i64>, frame_steps: u64) -> Watch {
+        let mut cpu = Computer::new(program);
+        cpu.watch = Some(Watch::new(frame_steps));
+        cpu.run();
+        cpu.watch.unwrap()
+    }
+
+    #[test]
+    fn test_regions() {
+        // [9] = [9] + [10], then a relative-mode write to base + 3
+        let w = watch(vec![1, 9, 10, 9, 21101, 1, 1, 3, 99, 5, 6], 100);
+        assert_eq!(
+            w.regions(),
+            vec![(0..9, Region::Code, None), (9..11, Region::Globals, None),]
+        );
+        let counts = &w.frames()[0].counts;
+        assert_eq!(counts[&9], [0, 1, 1]);
+        assert_eq!(counts[&10], [0, 1, 0]);
+    }
+
+    #[test]
+    fn test_stack_and_labels() {
+        // base = 20; [base + 0] = 7; output [base + 0]
+        let mut w = watch(vec![109, 20, 21101, 3, 4, 0, 204, 0, 99], 100);
+        assert_eq!(w.region(20), Some(Region::Stack));
+        w.label("scratch", 20..22, Region::Globals);
+        assert_eq!(w.region(20), Some(Region::Globals));
+        assert_eq!(
+            w.legend(),
+            "     0..9      Code\n    20..22     Globals scratch\n"
+        );
+    }
+
+    #[test]
+    fn test_frames() {
+        let w = watch(vec![1101, 1, 1, 5, 1101, 0, 0, 0, 99], 1);
+        assert_eq!(w.frames().len(), 4);
+        assert_eq!(w.frames()[1].step, 1);
+        assert_eq!(w.frames()[1].counts[&5], [1, 0, 0]);
+    }
+
+    #[test]
+    fn test_invalid_address() {
+        // [-1] = 1 + 1
+        let mut cpu = Computer::new(vec![1101, 1, 1, -1, 99]);
+        cpu.watch = Some(Watch::new(100));
+        assert!(cpu.try_run().is_err());
+        let w = cpu.watch.unwrap();
+        assert_eq!(w.regions(), vec![(0..4, Region::Code, None)]);
+        assert_eq!(w.render_ppm(0, 4, 1).len(), b"P6\n4 1\n255\n".len() + 4 * 3);
+    }
+
+    #[test]
+    fn test_render_ppm() {
+        let w = watch(vec![1101, 1, 1, 5, 99, 0], 100);
+        let ppm = w.render_ppm(0, 3, 2);
+        let header = b"P6\n6 4\n255\n";
+        assert_eq!(&ppm[..header.len()], header);
+        assert_eq!(ppm.len(), header.len() + 6 * 4 * 3);
+        // address 5 is written once, as busy as the busiest address
+        let pixel = header.len() + (2 * 6 + 4) * 3;
+        assert_eq!(ppm[pixel], 255);
+    }
+}